@@ -1,15 +1,39 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use penguin::run_code;
+use penguin::{compile, compile_with_options, Options};
 use std::fs;
 
+const VM: Options = Options {
+    strict: false,
+    vm: true,
+    no_fold: false,
+};
+
+//parsing and running are measured apart, the program is parsed once and run on every iteration
+fn bench_program(c: &mut Criterion, name: &str, path: &str) {
+    let code: String = fs::read_to_string(path).unwrap();
+    c.bench_function(&format!("{name}_parse"), |b| {
+        b.iter(|| compile(black_box(&code)))
+    });
+    c.bench_function(&format!("{name}_parse_vm"), |b| {
+        b.iter(|| compile_with_options(black_box(&code), VM))
+    });
+    let program = compile(&code).unwrap();
+    c.bench_function(name, |b| b.iter(|| program.run()));
+    let program = compile_with_options(&code, VM).unwrap();
+    c.bench_function(&format!("{name}_vm"), |b| b.iter(|| program.run()));
+}
+
 pub fn bench_1(c: &mut Criterion) {
-    let code: String = fs::read_to_string("examples/prime.pn").unwrap();
-    c.bench_function("bench_1", |b| b.iter(|| run_code(black_box(&code))));
+    bench_program(c, "bench_1", "examples/prime.pn");
 }
 pub fn bench_2(c: &mut Criterion) {
-    let code: String = fs::read_to_string("examples/sum.pn").unwrap();
-    c.bench_function("bench_2", |b| b.iter(|| run_code(black_box(&code))));
+    bench_program(c, "bench_2", "examples/sum.pn");
+}
+
+//the loops of bench_2 written with `for`
+pub fn bench_3(c: &mut Criterion) {
+    bench_program(c, "bench_3", "examples/sum_for.pn");
 }
 
-criterion_group!(benches, bench_1, bench_2);
+criterion_group!(benches, bench_1, bench_2, bench_3);
 criterion_main!(benches);
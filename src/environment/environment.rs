@@ -1,140 +1,277 @@
 extern crate fxhash;
 
-use std::rc::Rc;
-
-use anyhow::{anyhow, Result};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
 use fxhash::FxHashMap;
 
-use crate::parser::node::{function::Func, primitive::Primitive};
+use crate::error::{ControlFlowError, NameError, ParseError, Result};
+use crate::parser::node::{
+    expression::OpType, function::Closure, primitive::Primitive, structure::StructDef,
+};
 
-#[derive(Debug)]
-enum EnvironmentError {
-    ReDeclaration,
-    NotDeclareation,
+//every function call gets its own frame whose parent is the frame the function was created in,
+//the resolver gives every local variable a slot in the frame of its function or of the block
+//capturing it, and the number of frames to walk up to reach it from where it is used
+#[derive(Debug, Default)]
+pub struct Frame {
+    //`None` until the declaration of the slot has run
+    slots: RefCell<Vec<Option<Primitive>>>,
+    parent: Option<Rc<Frame>>,
 }
 
-impl std::fmt::Display for EnvironmentError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::ReDeclaration => write!(f, "variable has been previously declared"),
-            Self::NotDeclareation => write!(f, "variable was not declared"),
+impl Frame {
+    fn new(parent: Rc<Frame>, slots: usize) -> Self {
+        Frame {
+            slots: RefCell::new(vec![None; slots]),
+            parent: Some(parent),
+        }
+    }
+    //a closure kept in a slot of the frame it was created in holds the frame, so the two would
+    //keep each other alive forever. When the frame is left and nothing but such closures refers to
+    //it, no code can reach its variables anymore and the slots are emptied to free both
+    fn release(self: Rc<Self>) {
+        let owned = match Rc::strong_count(&self) {
+            1 => return,
+            count => count - 1,
+        };
+        let held = self
+            .slots
+            .borrow()
+            .iter()
+            .filter(|v| matches!(v, Some(Primitive::Function(v)) if self.is_owned_by(v)))
+            .count();
+        if held == owned {
+            drop(self.slots.take());
+        }
+    }
+    //whether `closure` can only be reached from the frame and refers to it, directly or through
+    //blocks nested in it that nothing else holds
+    fn is_owned_by(self: &Rc<Self>, closure: &Rc<Closure>) -> bool {
+        let Closure::Script { env, .. } = &**closure else {
+            return false;
+        };
+        if Rc::strong_count(closure) != 1 {
+            return false;
+        }
+        let mut frame = env;
+        while !Rc::ptr_eq(frame, self) {
+            match &frame.parent {
+                Some(parent) if Rc::strong_count(frame) == 1 => frame = parent,
+                _ => return false,
+            }
         }
+        true
     }
 }
 
+//how deep calls may nest. Each call takes host stack in both engines, this keeps release builds
+//well within the 8MB main thread
+pub const MAX_CALL_DEPTH: usize = 1000;
+
 #[derive(Debug, Default)]
-pub struct Environment<'a> {
-    scope_depth: usize,
-    function_scope: Vec<usize>,
-    scope_stack: Vec<(Var, usize)>,
-    var_mp: FxHashMap<&'a str, Var>,
-    variable_mp: Vec<Vec<(Primitive, usize)>>,
-    function_mp: Vec<Vec<(Rc<Func>, usize)>>,
+pub struct Environment {
+    //names are owned so entries of a session can be dropped once they are parsed
+    var_mp: FxHashMap<Rc<str>, Var>,
+    //names indexed by `Var`, used to report errors
+    names: Vec<Rc<str>>,
+    struct_mp: FxHashMap<Var, Rc<StructDef>>,
+    //variables declared at the top level of the program, indexed by `Var`
+    globals: Vec<Option<Primitive>>,
+    frame: Rc<Frame>,
+    call_stack: Vec<Rc<Frame>>,
+    //function calls currently running
+    calls: usize,
 }
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Var(usize);
 
-impl<'a> Environment<'a> {
-    pub fn register(&mut self, var: &'a str) -> Var {
+//where a variable is stored, names the resolver does not find in any enclosing block are globals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    Global(Var),
+    Local { depth: u32, slot: u32 },
+}
+
+//a variable used in the code, the address is filled in by the resolver once the code is parsed
+#[derive(Debug)]
+pub struct Variable {
+    var: Var,
+    address: Cell<Address>,
+}
+
+impl Variable {
+    pub fn new(var: Var) -> Self {
+        Self {
+            var,
+            address: Cell::new(Address::Global(var)),
+        }
+    }
+    pub fn var(&self) -> Var {
+        self.var
+    }
+    pub fn address(&self) -> Address {
+        self.address.get()
+    }
+    pub fn set_address(&self, address: Address) {
+        self.address.set(address)
+    }
+}
+
+impl Environment {
+    pub fn register(&mut self, var: &str) -> Var {
         match self.var_mp.get(var) {
             Some(v) => *v,
             None => {
-                self.var_mp.insert(var, Var(self.var_mp.len()));
+                let name = Rc::from(var);
+                self.var_mp.insert(Rc::clone(&name), Var(self.var_mp.len()));
+                self.names.push(name);
                 Var(self.var_mp.len() - 1)
             }
         }
     }
-    pub fn init(&mut self) {
-        self.function_scope.push(0);
-        self.variable_mp
-            .resize(self.var_mp.len(), Default::default());
-        self.function_mp
-            .resize(self.var_mp.len(), Default::default());
-    }
-    pub fn check_declare(&self, var: Var) -> Result<()> {
-        if let Some((_, depth)) = self.variable_mp[var.0].last() {
-            if *depth == self.scope_depth {
-                return Err(anyhow!(EnvironmentError::ReDeclaration));
-            }
-        }
-        if let Some((_, depth)) = self.function_mp[var.0].last() {
-            if *depth == self.scope_depth {
-                return Err(anyhow!(EnvironmentError::ReDeclaration));
-            }
+    pub fn name(&self, var: Var) -> &str {
+        &self.names[var.0]
+    }
+    //struct declarations are hoisted, they are collected while parsing and are visible everywhere
+    pub fn register_struct(&mut self, var: &str, def: StructDef) -> Result<()> {
+        let var = self.register(var);
+        if self.struct_mp.contains_key(&var) {
+            return Err(ParseError::DuplicateStruct(def.name().to_string()).into());
         }
+        self.struct_mp.insert(var, Rc::new(def));
         Ok(())
     }
-    pub fn subscribe_func(&mut self, var: Var, value: Rc<Func>) -> Result<()> {
-        self.check_declare(var)?;
-        self.function_mp[var.0].push((value, self.scope_depth));
-        self.scope_stack.push((var, self.scope_depth));
-        Ok(())
+    pub fn get_struct(&self, var: Var) -> Option<Rc<StructDef>> {
+        self.struct_mp.get(&var).cloned()
     }
-
-    pub fn subscribe_var(&mut self, var: Var, value: Primitive) -> Result<()> {
-        self.check_declare(var)?;
-        self.variable_mp[var.0].push((value, self.scope_depth));
-        self.scope_stack.push((var, self.scope_depth));
-        Ok(())
+    //makes room for the slots the top level code uses outside of functions
+    pub fn reserve(&mut self, slots: usize) {
+        let mut frame = self.frame.slots.borrow_mut();
+        if frame.len() < slots {
+            frame.resize(slots, None);
+        }
     }
-    pub fn get_var(&'a self, var: Var) -> Result<Primitive> {
-        self.variable_mp[var.0]
-            .last()
-            .and_then(|(v, depth)| {
-                if depth < self.function_scope.last().unwrap() {
-                    None
-                } else {
-                    Some(*v)
+    //forgets every value, the names, structs and reserved slots are kept to run the program again
+    pub fn reset(&mut self) {
+        let top = self.call_stack.first().unwrap_or(&self.frame);
+        let slots = top.slots.borrow().len();
+        self.frame = Rc::new(Frame {
+            slots: RefCell::new(vec![None; slots]),
+            parent: None,
+        });
+        self.call_stack.clear();
+        self.calls = 0;
+        self.globals.clear();
+    }
+    #[inline(always)]
+    fn frame_at(&self, depth: u32) -> &Rc<Frame> {
+        let mut frame = &self.frame;
+        for _ in 0..depth {
+            frame = frame.parent.as_ref().unwrap();
+        }
+        frame
+    }
+    //a local declared twice in the same block is caught by the resolver, globals are checked here
+    //since the later entries of a session declare them too
+    #[inline(always)]
+    pub fn declare_var(&mut self, address: Address, value: Primitive) -> Result<()> {
+        match address {
+            Address::Global(var) => {
+                if self.globals.len() <= var.0 {
+                    self.globals.resize(var.0 + 1, None);
                 }
-            })
-            .ok_or_else(|| anyhow!(EnvironmentError::NotDeclareation))
-    }
-    pub fn get_ref(&'a self, var: Var) -> Result<Primitive> {
-        self.variable_mp[var.0]
-            .last()
-            .map(|(v, _)| *v)
-            .ok_or_else(|| anyhow!(EnvironmentError::NotDeclareation))
-    }
-    pub fn get_func(&self, var: Var) -> Result<Rc<Func>> {
-        self.function_mp[var.0]
-            .last()
-            .and_then(|(v, depth)| {
-                if depth < self.function_scope.last().unwrap() {
-                    None
-                } else {
-                    Some(v.clone())
+                if self.globals[var.0].is_some() {
+                    return Err(NameError::ReDeclaration.into());
                 }
-            })
-            .ok_or_else(|| anyhow!(EnvironmentError::NotDeclareation))
-    }
-    pub fn assign_var(&mut self, var: Var, value: Primitive) -> Result<()> {
-        *self.variable_mp[var.0]
-            .last_mut()
-            .map(|(v, _)| v)
-            .ok_or_else(|| anyhow!(EnvironmentError::NotDeclareation))? = value;
+                self.globals[var.0] = Some(value);
+            }
+            Address::Local { depth, slot } => {
+                self.frame_at(depth).slots.borrow_mut()[slot as usize] = Some(value)
+            }
+        }
         Ok(())
     }
-    pub fn open_scope(&mut self) {
-        self.scope_depth += 1;
+    #[inline(always)]
+    pub fn get_var(&self, address: Address) -> Result<Primitive> {
+        match address {
+            Address::Global(var) => self.globals.get(var.0).cloned().flatten(),
+            Address::Local { depth, slot } => {
+                self.frame_at(depth).slots.borrow()[slot as usize].clone()
+            }
+        }
+        .ok_or_else(|| NameError::NotDeclared.into())
     }
-    pub fn close_scope(&mut self) {
-        while let Some((var, depth)) = self.scope_stack.last() {
-            if *depth == self.scope_depth {
-                self.variable_mp[var.0].pop();
-                self.scope_stack.pop();
-            } else {
-                break;
+    #[inline(always)]
+    pub fn assign_var(&mut self, address: Address, value: Primitive) -> Result<()> {
+        let declared = match address {
+            Address::Global(var) => self
+                .globals
+                .get_mut(var.0)
+                .and_then(|v| v.as_mut())
+                .map(|v| v.set(value)),
+            Address::Local { depth, slot } => self.frame_at(depth).slots.borrow_mut()
+                [slot as usize]
+                .as_mut()
+                .map(|v| v.set(value)),
+        };
+        declared.ok_or_else(|| NameError::NotDeclared.into())
+    }
+    //an assignment with an operator, the value is combined with `rhs` where it is stored
+    #[inline(always)]
+    pub fn update_var(
+        &mut self,
+        address: Address,
+        rhs: &Primitive,
+        op: &OpType,
+        strict: bool,
+    ) -> Result<()> {
+        let update = |v: Option<&mut Primitive>| -> Result<()> {
+            let v = v.ok_or(NameError::NotDeclared)?;
+            let value = v.evaluate_binary(rhs, op, strict)?;
+            v.set(value);
+            Ok(())
+        };
+        match address {
+            Address::Global(var) => update(self.globals.get_mut(var.0).and_then(|v| v.as_mut())),
+            Address::Local { depth, slot } => {
+                update(self.frame_at(depth).slots.borrow_mut()[slot as usize].as_mut())
             }
         }
-        self.scope_depth -= 1;
     }
-    pub fn open_function_scope(&mut self) {
-        self.open_scope();
-        self.function_scope.push(self.scope_depth);
+    pub fn frame(&self) -> Rc<Frame> {
+        Rc::clone(&self.frame)
+    }
+    //a block that creates closures gets a frame of its own, so its variables stay alive for the
+    //closures and every run of the block (e.g. each loop iteration) hands out fresh variables.
+    //other blocks keep their variables in the frame they are in
+    pub fn open_scope(&mut self, slots: usize) {
+        self.open_function_scope(self.frame(), slots);
+    }
+    pub fn close_scope(&mut self) {
+        self.close_function_scope();
+    }
+    pub fn open_function_scope(&mut self, parent: Rc<Frame>, slots: usize) {
+        let frame = Rc::new(Frame::new(parent, slots));
+        self.call_stack
+            .push(std::mem::replace(&mut self.frame, frame));
     }
     pub fn close_function_scope(&mut self) {
-        self.close_scope();
-        self.function_scope.pop();
+        let frame = std::mem::replace(&mut self.frame, self.call_stack.pop().unwrap());
+        frame.release();
+    }
+    pub fn open_call(&mut self, parent: Rc<Frame>, slots: usize) -> Result<()> {
+        if self.calls == MAX_CALL_DEPTH {
+            return Err(ControlFlowError::CallDepth(MAX_CALL_DEPTH).into());
+        }
+        self.calls += 1;
+        self.open_function_scope(parent, slots);
+        Ok(())
+    }
+    pub fn close_call(&mut self) {
+        self.calls -= 1;
+        self.close_function_scope();
     }
 }
@@ -1 +1,561 @@
+use pest::error::{Error, ErrorVariant, LineColLocation};
+use pest::{Position, Span};
 
+//errors are passed around boxed inside the interpreter, an unboxed `PenguinError` would make
+//every `Result` on the hot path several words bigger
+pub type Result<T> = std::result::Result<T, Box<PenguinError>>;
+
+//byte offsets of a node in the source code, kept instead of a pest `Span` so that the nodes
+//don't need to borrow the source
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    start: usize,
+    end: usize,
+}
+
+impl Location {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    pub fn end(&self) -> usize {
+        self.end
+    }
+}
+
+impl From<Span<'_>> for Location {
+    fn from(span: Span<'_>) -> Self {
+        Self::new(span.start(), span.end())
+    }
+}
+
+//where an error happened, `rendered` is filled in once the error leaves the interpreter
+//and the source code is at hand
+#[derive(Debug)]
+pub struct Diagnostic {
+    location: Location,
+    identifier: Option<String>,
+    rendered: Option<Error<()>>,
+}
+
+//every error Penguin reports, grouped by what went wrong. The code of an error never changes
+//between releases so embedders can rely on it
+#[derive(Debug)]
+pub enum PenguinError {
+    Parse {
+        error: ParseError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Name {
+        error: NameError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Type {
+        error: TypeError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Arity {
+        error: ArityError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Arithmetic {
+        error: ArithmeticError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Index {
+        error: IndexError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    ControlFlow {
+        error: ControlFlowError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Io {
+        error: std::io::Error,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Native {
+        error: NativeError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Match {
+        error: MatchError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+    Thrown {
+        error: ThrownError,
+        diagnostic: Option<Box<Diagnostic>>,
+    },
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Syntax,
+    InvalidLiteral(String),
+    DuplicateStruct(String),
+    DuplicateStructField { name: String, field: String },
+    BuiltinArgument(String),
+    DuplicateArgument(String),
+    UnknownLabel(String),
+    MissingElse,
+    //the keyword of the statement leaving the block
+    LeavesValue(&'static str),
+    //builtins are resolved by name, so nothing else may take theirs
+    BuiltinName(String),
+}
+
+#[derive(Debug)]
+pub enum NameError {
+    ReDeclaration,
+    NotDeclared,
+    UndeclaredStruct(String),
+}
+
+#[derive(Debug)]
+pub enum TypeError {
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    UnsupportedOperation {
+        op: &'static str,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    UnknownField {
+        name: String,
+        field: String,
+    },
+    MissingField {
+        name: String,
+        field: String,
+    },
+    DuplicateField {
+        name: String,
+        field: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct ArityError {
+    pub expected: usize,
+    pub found: usize,
+}
+
+#[derive(Debug)]
+pub enum ArithmeticError {
+    Overflow { op: &'static str },
+    DivisionByZero,
+    ShiftOutOfRange(i64),
+    IntegerTooLarge,
+    ZeroStep,
+}
+
+#[derive(Debug)]
+pub enum IndexError {
+    //the index as it was written, a big int too
+    OutOfBounds { index: String, len: usize },
+    EmptyArray,
+    KeyNotFound(String),
+}
+
+//a function registered by the host program failed
+#[derive(Debug)]
+pub struct NativeError {
+    pub message: String,
+}
+
+//no arm of a `match` accepts the value, which is kept printed
+#[derive(Debug)]
+pub struct MatchError {
+    pub value: String,
+}
+
+//a value thrown by the script that no `catch` took. The value itself stays with the program
+//until it is caught, `slot` is where
+#[derive(Debug)]
+pub struct ThrownError {
+    pub value: String,
+    pub(crate) slot: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ControlFlowError {
+    ReturnOutsideFunction,
+    ContinueOutsideLoop,
+    BreakOutsideLoop,
+    //the limit on nested calls, reached by runaway recursion before the stack of the host runs out
+    CallDepth(usize),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax => write!(f, "Parse error occurred"),
+            Self::InvalidLiteral(v) => write!(f, "invalid literal `{}`", v),
+            Self::DuplicateStruct(name) => {
+                write!(f, "struct `{}` has been previously declared", name)
+            }
+            Self::DuplicateStructField { name, field } => write!(
+                f,
+                "field `{}` is declared more than once in struct `{}`",
+                field, name
+            ),
+            Self::BuiltinArgument(name) => write!(
+                f,
+                "builtin function `{}` only accepts expressions as arguments",
+                name
+            ),
+            Self::DuplicateArgument(name) => {
+                write!(f, "argument `{}` is declared more than once", name)
+            }
+            Self::UnknownLabel(name) => {
+                write!(f, "no loop labeled `{}` encloses this statement", name)
+            }
+            Self::MissingElse => write!(f, "an `if` used as a value needs an `else`"),
+            Self::LeavesValue(keyword) => {
+                write!(f, "`{}` cannot leave a block used as a value", keyword)
+            }
+            Self::BuiltinName(name) => {
+                write!(f, "`{}` is a builtin function and cannot be declared", name)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for NameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReDeclaration => write!(f, "variable has been previously declared"),
+            Self::NotDeclared => write!(f, "variable was not declared"),
+            Self::UndeclaredStruct(name) => write!(f, "struct `{}` was not declared", name),
+        }
+    }
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TypeMismatch { expected, found } => {
+                write!(f, "expected a value of type {}, found {}", expected, found)
+            }
+            Self::UnsupportedOperation { op, lhs, rhs } => {
+                write!(
+                    f,
+                    "unsupported operation `{}` between {} and {}",
+                    op, lhs, rhs
+                )
+            }
+            Self::UnknownField { name, field } => {
+                write!(f, "struct `{}` has no field `{}`", name, field)
+            }
+            Self::MissingField { name, field } => {
+                write!(f, "missing field `{}` in initializer of `{}`", field, name)
+            }
+            Self::DuplicateField { name, field } => {
+                write!(
+                    f,
+                    "field `{}` of `{}` is specified more than once",
+                    field, name
+                )
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ArityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.expected < self.found {
+            return write!(f, "too many arguments in function call");
+        }
+        write!(f, "too few arguments in function call")
+    }
+}
+
+impl std::fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overflow { op } => write!(f, "integer overflow in `{}`", op),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::ShiftOutOfRange(v) => write!(f, "shift amount {} is out of range", v),
+            Self::IntegerTooLarge => write!(f, "integer does not fit in 64 bits"),
+            Self::ZeroStep => write!(f, "the step of a range cannot be zero"),
+        }
+    }
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "index {} is out of bounds for array of length {}",
+                    index, len
+                )
+            }
+            Self::EmptyArray => write!(f, "cannot pop from an empty array"),
+            Self::KeyNotFound(key) => write!(f, "key {} was not found in dict", key),
+        }
+    }
+}
+
+impl std::fmt::Display for NativeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "native function failed: {}", self.message)
+    }
+}
+
+impl std::fmt::Display for MatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no arm of the match accepts {}", self.value)
+    }
+}
+
+impl std::fmt::Display for ThrownError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "uncaught exception: {}", self.value)
+    }
+}
+
+impl std::fmt::Display for ControlFlowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReturnOutsideFunction => {
+                write!(f, "a return statement may only be used within a function")
+            }
+            Self::ContinueOutsideLoop => {
+                write!(f, "a continue statement may only be used within a loop")
+            }
+            Self::BreakOutsideLoop => {
+                write!(f, "a break statement may only be used within a loop")
+            }
+            Self::CallDepth(limit) => {
+                write!(f, "calls are nested more than {} deep", limit)
+            }
+        }
+    }
+}
+
+macro_rules! impl_from_error {
+    ($($error:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$error> for PenguinError {
+                fn from(error: $error) -> Self {
+                    Self::$variant {
+                        error,
+                        diagnostic: None,
+                    }
+                }
+            }
+            impl From<$error> for Box<PenguinError> {
+                fn from(error: $error) -> Self {
+                    Box::new(PenguinError::from(error))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_error!(
+    ParseError => Parse,
+    NameError => Name,
+    TypeError => Type,
+    ArityError => Arity,
+    ArithmeticError => Arithmetic,
+    IndexError => Index,
+    ControlFlowError => ControlFlow,
+    std::io::Error => Io,
+    NativeError => Native,
+    MatchError => Match,
+    ThrownError => Thrown,
+);
+
+impl PenguinError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Parse { error, .. } => match error {
+                ParseError::Syntax => "E0001",
+                ParseError::InvalidLiteral(_) => "E0002",
+                ParseError::DuplicateStruct(_) => "E0003",
+                ParseError::DuplicateStructField { .. } => "E0004",
+                ParseError::BuiltinArgument(_) => "E0005",
+                ParseError::DuplicateArgument(_) => "E0006",
+                ParseError::UnknownLabel(_) => "E0007",
+                ParseError::MissingElse => "E0008",
+                ParseError::LeavesValue(_) => "E0009",
+                ParseError::BuiltinName(_) => "E0010",
+            },
+            Self::Name { error, .. } => match error {
+                NameError::NotDeclared => "E0101",
+                NameError::ReDeclaration => "E0102",
+                NameError::UndeclaredStruct(_) => "E0103",
+            },
+            Self::Type { error, .. } => match error {
+                TypeError::TypeMismatch { .. } => "E0201",
+                TypeError::UnsupportedOperation { .. } => "E0202",
+                TypeError::UnknownField { .. } => "E0203",
+                TypeError::MissingField { .. } => "E0204",
+                TypeError::DuplicateField { .. } => "E0205",
+            },
+            Self::Arity { .. } => "E0301",
+            Self::Arithmetic { error, .. } => match error {
+                ArithmeticError::Overflow { .. } => "E0401",
+                ArithmeticError::DivisionByZero => "E0402",
+                ArithmeticError::ShiftOutOfRange(_) => "E0403",
+                ArithmeticError::IntegerTooLarge => "E0404",
+                ArithmeticError::ZeroStep => "E0405",
+            },
+            Self::Index { error, .. } => match error {
+                IndexError::OutOfBounds { .. } => "E0501",
+                IndexError::EmptyArray => "E0502",
+                IndexError::KeyNotFound(_) => "E0503",
+            },
+            Self::ControlFlow { error, .. } => match error {
+                ControlFlowError::ReturnOutsideFunction => "E0601",
+                ControlFlowError::ContinueOutsideLoop => "E0602",
+                ControlFlowError::BreakOutsideLoop => "E0603",
+                ControlFlowError::CallDepth(_) => "E0604",
+            },
+            Self::Io { .. } => "E0701",
+            Self::Native { .. } => "E0801",
+            Self::Match { .. } => "E0901",
+            Self::Thrown { .. } => "E1001",
+        }
+    }
+    //what went wrong, in the words a script catching the error sees
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Parse { .. } => "parse",
+            Self::Name { .. } => "name",
+            Self::Type { .. } => "type",
+            Self::Arity { .. } => "arity",
+            Self::Arithmetic { .. } => "arithmetic",
+            Self::Index { .. } => "index",
+            Self::ControlFlow { .. } => "control_flow",
+            Self::Io { .. } => "io",
+            Self::Native { .. } => "native",
+            Self::Match { .. } => "match",
+            Self::Thrown { .. } => "throw",
+        }
+    }
+    //whether a `catch` takes the error. A flow statement outside of its loop or function is a
+    //mistake in the program, like a parse error. Too deep a recursion depends on the input, so it
+    //can be caught
+    pub fn is_catchable(&self) -> bool {
+        match self {
+            Self::Parse { .. } => false,
+            Self::ControlFlow { error, .. } => matches!(error, ControlFlowError::CallDepth(_)),
+            _ => true,
+        }
+    }
+    fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            Self::Parse { diagnostic, .. }
+            | Self::Name { diagnostic, .. }
+            | Self::Type { diagnostic, .. }
+            | Self::Arity { diagnostic, .. }
+            | Self::Arithmetic { diagnostic, .. }
+            | Self::Index { diagnostic, .. }
+            | Self::ControlFlow { diagnostic, .. }
+            | Self::Io { diagnostic, .. }
+            | Self::Native { diagnostic, .. }
+            | Self::Match { diagnostic, .. }
+            | Self::Thrown { diagnostic, .. } => diagnostic.as_deref(),
+        }
+    }
+    fn diagnostic_mut(&mut self) -> &mut Option<Box<Diagnostic>> {
+        match self {
+            Self::Parse { diagnostic, .. }
+            | Self::Name { diagnostic, .. }
+            | Self::Type { diagnostic, .. }
+            | Self::Arity { diagnostic, .. }
+            | Self::Arithmetic { diagnostic, .. }
+            | Self::Index { diagnostic, .. }
+            | Self::ControlFlow { diagnostic, .. }
+            | Self::Io { diagnostic, .. }
+            | Self::Native { diagnostic, .. }
+            | Self::Match { diagnostic, .. }
+            | Self::Thrown { diagnostic, .. } => diagnostic,
+        }
+    }
+    //the message without any location
+    pub fn message(&self) -> String {
+        let message = match self {
+            Self::Parse { error, .. } => error.to_string(),
+            Self::Name { error, .. } => error.to_string(),
+            Self::Type { error, .. } => error.to_string(),
+            Self::Arity { error, .. } => error.to_string(),
+            Self::Arithmetic { error, .. } => error.to_string(),
+            Self::Index { error, .. } => error.to_string(),
+            Self::ControlFlow { error, .. } => error.to_string(),
+            Self::Io { error, .. } => error.to_string(),
+            Self::Native { error, .. } => error.to_string(),
+            Self::Match { error, .. } => error.to_string(),
+            Self::Thrown { error, .. } => error.to_string(),
+        };
+        match self.identifier() {
+            Some(identifier) => format!("{}: `{}`", message, identifier),
+            None => message,
+        }
+    }
+    pub fn identifier(&self) -> Option<&str> {
+        self.diagnostic()?.identifier.as_deref()
+    }
+    //1-based line and column of the start of the error
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        match self.diagnostic()?.rendered.as_ref()?.line_col {
+            LineColLocation::Pos(v) | LineColLocation::Span(v, _) => Some(v),
+        }
+    }
+    //errors are located by the innermost node they pass through, outer nodes leave them untouched
+    pub fn locate(
+        mut self: Box<Self>,
+        location: Location,
+        identifier: impl FnOnce() -> Option<String>,
+    ) -> Box<Self> {
+        let diagnostic = self.diagnostic_mut();
+        if diagnostic.is_none() {
+            *diagnostic = Some(Box::new(Diagnostic {
+                location,
+                identifier: identifier(),
+                rendered: None,
+            }));
+        }
+        self
+    }
+    pub fn render(mut self: Box<Self>, code: &str) -> Box<Self> {
+        let message = self.message();
+        if let Some(diagnostic) = self.diagnostic_mut() {
+            if diagnostic.rendered.is_none() {
+                let Location { start, end } = diagnostic.location;
+                let variant = ErrorVariant::CustomError { message };
+                diagnostic.rendered = match start == end {
+                    true => Position::new(code, start).map(|v| Error::new_from_pos(variant, v)),
+                    false => Span::new(code, start, end).map(|v| Error::new_from_span(variant, v)),
+                };
+            }
+        }
+        self
+    }
+    //adds the file name to the rendered error
+    pub fn with_path(mut self, path: &str) -> Self {
+        if let Some(diagnostic) = self.diagnostic_mut() {
+            diagnostic.rendered = diagnostic.rendered.take().map(|v| v.with_path(path));
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for PenguinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.diagnostic().and_then(|v| v.rendered.as_ref()) {
+            Some(rendered) => write!(f, "{}", rendered),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for PenguinError {}
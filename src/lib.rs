@@ -1,63 +1,277 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
 
-use anyhow::{anyhow, Result};
-use environment::environment::Environment;
+use environment::environment::{Address, Environment, Var};
+use error::{ControlFlowError, Location, ParseError, Result, ThrownError};
 use parser::{
     ast::ASTNode,
     node::{
+        builtin::Builtin,
+        exception::error_value,
         expression::ExpressionPool,
+        function::Closure,
+        native::NativeFunction,
         primitive::Primitive,
-        scope::{FlowStatement, ScopeError},
+        scope::{FlowStatement, Scope},
     },
     parser::parse_ast,
 };
+use vm::{bytecode::Chunk, compiler::Compiler, vm::Vm};
 
 pub mod environment;
 pub mod error;
 pub mod parser;
+pub mod session;
+pub mod vm;
+
+pub use error::PenguinError;
+pub use parser::node::native::{FromPrimitive, NativeFn, NativeResult};
+
+//settings that change how a program is evaluated
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    //rejects arithmetic on bools, conditions on ints and equality between different types
+    //instead of converting between bools and ints
+    pub strict: bool,
+    //compiles the program to bytecode and runs it on the vm instead of walking the syntax tree
+    pub vm: bool,
+    //keeps expressions as written instead of folding constants and simplifying operations
+    //with them, to debug the folding
+    pub no_fold: bool,
+}
 
 pub struct ProgramState<'a> {
     expr_pool: ExpressionPool,
-    environment: RefCell<Environment<'a>>,
+    environment: RefCell<Environment>,
+    options: Options,
+    //where `print` and `println` write to, stdout unless the host program gives another writer
+    output: RefCell<Box<dyn Write + 'a>>,
+    //values thrown by the script and not caught yet, the errors carrying them only hold their slot
+    thrown: RefCell<Vec<Primitive>>,
 }
 impl<'a> ProgramState<'a> {
-    pub fn new(expr_pool: ExpressionPool, environment: RefCell<Environment<'a>>) -> Self {
+    pub fn new(expr_pool: ExpressionPool, environment: RefCell<Environment>) -> Self {
         ProgramState {
             expr_pool,
             environment,
+            options: Options::default(),
+            output: RefCell::new(Box::new(io::stdout())),
+            thrown: RefCell::new(Vec::new()),
+        }
+    }
+    pub fn print(&self, val: &Primitive, end: &str) -> Result<()> {
+        write!(self.output.borrow_mut(), "{}{}", val, end)?;
+        Ok(())
+    }
+    pub fn flush(&self) -> Result<()> {
+        self.output.borrow_mut().flush()?;
+        Ok(())
+    }
+    pub fn throw(&self, val: Primitive) -> Box<PenguinError> {
+        let mut thrown = self.thrown.borrow_mut();
+        let value = val.quoted();
+        thrown.push(val);
+        ThrownError {
+            value,
+            slot: thrown.len() - 1,
+        }
+        .into()
+    }
+    //the value a `catch` gets for an error, the thrown value or an `Error` struct for the
+    //errors of the interpreter
+    pub fn catch(&self, e: PenguinError) -> Primitive {
+        match e {
+            PenguinError::Thrown { error, .. } => {
+                //values thrown after it were dropped by a `finally` failing on its way
+                let mut thrown = self.thrown.borrow_mut();
+                let val = thrown.swap_remove(error.slot);
+                thrown.truncate(error.slot);
+                val
+            }
+            e => error_value(&e),
         }
     }
 }
 
-pub fn run_code(code: &str) -> Result<()> {
-    let (ast_root, mut program) = parse_ast(code)?;
-    program.expr_pool.shrink();
-    program.environment.borrow_mut().init();
-    if let ASTNode::Scope(v) = ast_root {
-        for node in v.code.iter() {
-            let mut flow_statement: Option<FlowStatement> = None;
-            match node {
-                ASTNode::Expr(v) => v.execute(&program).map(|_| ())?,
-                ASTNode::Declaration(v) => v.execute(&program)?,
-                ASTNode::Assignment(v) => v.execute(&program)?,
-                ASTNode::Scope(v) => flow_statement = v.execute(&program, false)?,
-                ASTNode::IfElse(v) => flow_statement = v.execute(&program)?,
-                ASTNode::WhileLoop(v) => flow_statement = v.execute(&program)?,
-                ASTNode::Output(v) => v.execute(&program)?,
-                ASTNode::BreakStatement => flow_statement = Some(FlowStatement::Break),
-                ASTNode::ReturnStatement(_) => {
-                    //flow statement here is only for error reporting so don't need to evaluate the expr inside it
-                    flow_statement = Some(FlowStatement::Return(Primitive::VOID))
-                }
-                ASTNode::ContinueStatement => flow_statement = Some(FlowStatement::Continue),
+//collects the output of a run for `run_to_string`
+struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//a parsed program that can be run any number of times, every run starts with no variables
+//declared so the runs don't see each other
+pub struct Program<'a> {
+    code: &'a str,
+    ast_root: ASTNode,
+    state: ProgramState<'a>,
+    //compiled up front when the program runs on the vm
+    chunk: Option<Chunk>,
+    //functions of the host program, declared as globals before every run
+    natives: Vec<(Var, Primitive)>,
+}
+
+impl<'a> Program<'a> {
+    //the writer the later runs print to
+    pub fn set_output(&mut self, output: impl Write + 'a) {
+        *self.state.output.get_mut() = Box::new(output);
+    }
+    //runs the program and returns what it printed instead of writing it to the output
+    pub fn run_to_string(&self) -> std::result::Result<String, PenguinError> {
+        let captured = Rc::new(RefCell::new(Vec::new()));
+        let output = Box::new(Capture(Rc::clone(&captured)));
+        let output = self.state.output.replace(output);
+        let res = self.run();
+        *self.state.output.borrow_mut() = output;
+        res?;
+        let captured = captured.borrow();
+        Ok(String::from_utf8_lossy(&captured).into_owned())
+    }
+    pub fn run(&self) -> std::result::Result<(), PenguinError> {
+        let mut environment = self.state.environment.borrow_mut();
+        environment.reset();
+        self.state.thrown.borrow_mut().clear();
+        for (var, native) in self.natives.iter() {
+            //names are only declared once, so this can't fail
+            let _ = environment.declare_var(Address::Global(*var), native.clone());
+        }
+        drop(environment);
+        let res = match &self.chunk {
+            Some(chunk) => Vm::new(&self.state).run(chunk).map(|_| ()),
+            None => run(&self.ast_root, &self.state),
+        };
+        //what was printed before an error is still written out
+        let flushed = self.state.flush();
+        res.and(flushed).map_err(|e| *e.render(self.code))
+    }
+}
+
+//sets up how programs are run for a host program that embeds Penguin. Functions of the host
+//are registered by name and scripts call them like their own functions, e.g.
+//`Interpreter::new().function("add", |a: i64, b: i64| a + b).run("println(add(1, 2));")`
+#[derive(Default)]
+pub struct Interpreter {
+    options: Options,
+    natives: Vec<(&'static str, Primitive)>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+    //the arity and the conversion of the arguments come from the types the closure takes, see
+    //`FromPrimitive`. A closure returning `Err` fails the call with a native error.
+    //registering a name again replaces the function, the name of a builtin fails the compilation
+    pub fn function<Args>(mut self, name: &'static str, f: impl NativeFn<Args>) -> Self {
+        let native = Primitive::Function(Rc::new(Closure::Native(Rc::new(NativeFunction::new(
+            name, f,
+        )))));
+        self.natives.retain(|(v, _)| *v != name);
+        self.natives.push((name, native));
+        self
+    }
+    pub fn compile<'a>(&self, code: &'a str) -> std::result::Result<Program<'a>, PenguinError> {
+        if let Some((name, _)) = self
+            .natives
+            .iter()
+            .find(|(v, _)| Builtin::from_name(v).is_some())
+        {
+            return Err(ParseError::BuiltinName(name.to_string()).into());
+        }
+        let (ast_root, mut state) = parse_ast(code).map_err(|e| *e)?;
+        state.expr_pool.shrink();
+        state.options = self.options;
+        if !self.options.no_fold {
+            state.expr_pool.fold(self.options.strict);
+        }
+        let chunk = match &ast_root {
+            ASTNode::Scope(v) if self.options.vm => {
+                Some(Compiler::new(&state.expr_pool).compile_program(&v.code, false))
             }
-            match flow_statement {
-                Some(FlowStatement::Break) => Err(anyhow!(ScopeError::BreakOutsideLoop))?,
-                Some(FlowStatement::Return(_)) => Err(anyhow!(ScopeError::ReturnOutsideFunction))?,
-                Some(FlowStatement::Continue) => Err(anyhow!(ScopeError::ContinueOutsideLoop))?,
-                None => {}
-            };
+            _ => None,
+        };
+        let natives = self
+            .natives
+            .iter()
+            .map(|(name, native)| {
+                (
+                    state.environment.borrow_mut().register(name),
+                    native.clone(),
+                )
+            })
+            .collect();
+        Ok(Program {
+            code,
+            ast_root,
+            state,
+            chunk,
+            natives,
+        })
+    }
+    pub fn run(&self, code: &str) -> std::result::Result<(), PenguinError> {
+        self.compile(code)?.run()
+    }
+    pub fn run_to_string(&self, code: &str) -> std::result::Result<String, PenguinError> {
+        self.compile(code)?.run_to_string()
+    }
+}
+
+pub fn compile(code: &str) -> std::result::Result<Program<'_>, PenguinError> {
+    compile_with_options(code, Options::default())
+}
+
+pub fn compile_with_options(
+    code: &str,
+    options: Options,
+) -> std::result::Result<Program<'_>, PenguinError> {
+    Interpreter::new().options(options).compile(code)
+}
+
+pub fn run_code(code: &str) -> std::result::Result<(), PenguinError> {
+    run_code_with_options(code, Options::default())
+}
+
+pub fn run_code_with_options(
+    code: &str,
+    options: Options,
+) -> std::result::Result<(), PenguinError> {
+    compile_with_options(code, options)?.run()
+}
+
+fn run(ast_root: &ASTNode, program: &ProgramState) -> Result<()> {
+    if let ASTNode::Scope(v) = ast_root {
+        for (node, location) in v.code.iter() {
+            run_statement(node, *location, program)?;
         }
     }
     Ok(())
 }
+
+fn run_statement(node: &ASTNode, location: Location, program: &ProgramState) -> Result<()> {
+    let flow_statement = match node {
+        //flow statement here is only for error reporting so don't need to evaluate the expr inside it
+        ASTNode::ReturnStatement(_) => Ok(Some(FlowStatement::Return(Primitive::VOID))),
+        _ => node.execute(program),
+    };
+    flow_statement
+        .and_then(|v| match v {
+            Some(FlowStatement::Break(_)) => Err(ControlFlowError::BreakOutsideLoop.into()),
+            Some(FlowStatement::Return(_)) => Err(ControlFlowError::ReturnOutsideFunction.into()),
+            Some(FlowStatement::Continue(_)) => Err(ControlFlowError::ContinueOutsideLoop.into()),
+            None => Ok(()),
+        })
+        .map_err(|e| Scope::locate(e, node, location, program))
+}
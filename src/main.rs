@@ -1,17 +1,94 @@
 use anyhow::{Context, Ok, Result};
 use clap::Parser;
-use penguin::run_code;
-use std::fs;
+use penguin::{
+    parser::node::primitive::Primitive,
+    run_code_with_options,
+    session::{is_incomplete, Session},
+    Options,
+};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use std::{fs, path::PathBuf};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    file: String,
+    /// Program to run, an interactive session is started when it is left out
+    file: Option<String>,
+    /// Reject arithmetic on bools, conditions on ints and equality between different types
+    #[arg(long)]
+    strict: bool,
+    /// Compile the program to bytecode and run it on the virtual machine
+    #[arg(long)]
+    vm: bool,
+    /// Keep constant expressions as written instead of folding them
+    #[arg(long)]
+    no_fold: bool,
 }
 
+//the host stack is spent by the interpreted program's calls, unoptimized builds take ~12KB per
+//call which the 8MB main thread can't give for the whole call depth limit
 fn main() -> Result<()> {
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(start)?
+        .join()
+        .unwrap()
+}
+
+fn start() -> Result<()> {
     let args = Args::parse();
-    let code = fs::read_to_string(args.file).context("Failed to read file")?;
-    run_code(&code)?;
+    let options = Options {
+        strict: args.strict,
+        vm: args.vm,
+        no_fold: args.no_fold,
+    };
+    let Some(file) = args.file else {
+        return repl(options);
+    };
+    let code = fs::read_to_string(&file).context("Failed to read file")?;
+    run_code_with_options(&code, options).map_err(|e| e.with_path(&file))?;
+    Ok(())
+}
+
+fn repl(options: Options) -> Result<()> {
+    let mut session = Session::new(options);
+    let mut editor = DefaultEditor::new()?;
+    let history = std::env::var_os("HOME").map(|v| PathBuf::from(v).join(".penguin_history"));
+    if let Some(path) = &history {
+        //there is no history yet on the first run
+        let _ = editor.load_history(path);
+    }
+    let mut entry = String::new();
+    loop {
+        let prompt = if entry.is_empty() { ">>> " } else { "... " };
+        match editor.readline(prompt) {
+            Result::Ok(line) => {
+                entry.push_str(&line);
+                entry.push('\n');
+                if is_incomplete(&entry) {
+                    continue;
+                }
+                let code = std::mem::take(&mut entry);
+                let code = code.trim_end();
+                if code.trim_start().is_empty() {
+                    continue;
+                }
+                editor.add_history_entry(code)?;
+                match session.run(code) {
+                    Result::Ok(Some(Primitive::String(v))) => println!("{:?}", v),
+                    Result::Ok(Some(v)) => println!("{}", v),
+                    Result::Ok(None) => {}
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            //ctrl-c drops the entry being written, ctrl-d leaves
+            Err(ReadlineError::Interrupted) => entry.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if let Some(path) = &history {
+        editor.save_history(path)?;
+    }
     Ok(())
 }
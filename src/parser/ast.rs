@@ -1,10 +1,20 @@
+use crate::error::{Location, Result};
+use crate::parser::resolver::Resolver;
+use crate::vm::{
+    bytecode::{Name, Op, Origin},
+    compiler::Compiler,
+};
+
+use crate::{environment::environment::Var, ProgramState};
+
 use super::node::{
-    conditional::IfElse,
+    conditional::{IfElse, Match},
     declaration::{Assignment, Declaration},
+    exception::Try,
     expression::Expr,
     io::Output,
-    looping::WhileLoop,
-    scope::Scope,
+    looping::{ForLoop, WhileLoop},
+    scope::{FlowStatement, Scope},
 };
 
 #[derive(Debug)]
@@ -15,8 +25,88 @@ pub enum ASTNode {
     Declaration(Declaration),
     Assignment(Assignment),
     IfElse(IfElse),
+    Match(Match),
     WhileLoop(WhileLoop),
-    BreakStatement,
+    ForLoop(ForLoop),
+    BreakStatement(Option<Var>),
     ReturnStatement(Expr),
-    ContinueStatement,
+    ContinueStatement(Option<Var>),
+    ThrowStatement(Expr),
+    Try(Try),
+}
+
+impl ASTNode {
+    pub fn execute(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
+        match self {
+            ASTNode::Expr(v) => v.execute(program).map(|_| None),
+            ASTNode::Declaration(v) => v.execute(program).map(|_| None),
+            ASTNode::Assignment(v) => v.execute(program).map(|_| None),
+            ASTNode::Scope(v) => v.execute(program, false),
+            ASTNode::IfElse(v) => v.execute(program),
+            ASTNode::Match(v) => v.execute(program),
+            ASTNode::WhileLoop(v) => v.execute(program),
+            ASTNode::ForLoop(v) => v.execute(program),
+            ASTNode::Output(v) => v.execute(program).map(|_| None),
+            ASTNode::BreakStatement(label) => Ok(Some(FlowStatement::Break(*label))),
+            ASTNode::ContinueStatement(label) => Ok(Some(FlowStatement::Continue(*label))),
+            ASTNode::ReturnStatement(v) => {
+                v.execute(program).map(|v| Some(FlowStatement::Return(v)))
+            }
+            ASTNode::ThrowStatement(v) => Err(program.throw(v.execute(program)?)),
+            ASTNode::Try(v) => v.execute(program),
+        }
+    }
+    pub fn compile(&self, compiler: &mut Compiler, location: Location) {
+        let origin = Origin::new(location, self.identifier().map(Name::Var));
+        match self {
+            ASTNode::Expr(v) => {
+                v.compile(compiler);
+                compiler.emit(Op::Pop, None);
+            }
+            ASTNode::Declaration(v) => v.compile(compiler, origin),
+            ASTNode::Assignment(v) => v.compile(compiler, origin),
+            ASTNode::Scope(v) => v.compile(compiler, false),
+            ASTNode::IfElse(v) => v.compile(compiler),
+            ASTNode::Match(v) => v.compile(compiler),
+            ASTNode::WhileLoop(v) => v.compile(compiler),
+            ASTNode::ForLoop(v) => v.compile(compiler),
+            ASTNode::Output(v) => v.compile(compiler, origin),
+            ASTNode::BreakStatement(label) => compiler.emit_break(*label),
+            ASTNode::ContinueStatement(label) => compiler.emit_continue(*label),
+            ASTNode::ReturnStatement(v) => {
+                v.compile(compiler);
+                compiler.emit_return();
+            }
+            ASTNode::ThrowStatement(v) => {
+                v.compile(compiler);
+                compiler.emit(Op::Throw, Some(origin));
+            }
+            ASTNode::Try(v) => v.compile(compiler),
+        }
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        match self {
+            ASTNode::Expr(v) | ASTNode::ReturnStatement(v) | ASTNode::ThrowStatement(v) => {
+                v.resolve(resolver)
+            }
+            ASTNode::Declaration(v) => v.resolve(resolver),
+            ASTNode::Assignment(v) => v.resolve(resolver),
+            ASTNode::Scope(v) => v.resolve(resolver, false),
+            ASTNode::IfElse(v) => v.resolve(resolver),
+            ASTNode::Match(v) => v.resolve(resolver),
+            ASTNode::WhileLoop(v) => v.resolve(resolver),
+            ASTNode::ForLoop(v) => v.resolve(resolver),
+            ASTNode::Try(v) => v.resolve(resolver),
+            ASTNode::Output(v) => v.resolve(resolver),
+            ASTNode::BreakStatement(_) | ASTNode::ContinueStatement(_) => {}
+        }
+    }
+    //the variable an error raised by the statement itself is reported on
+    pub fn identifier(&self) -> Option<Var> {
+        match self {
+            ASTNode::Declaration(v) => Some(v.var()),
+            ASTNode::Assignment(v) => Some(v.var()),
+            _ => None,
+        }
+    }
 }
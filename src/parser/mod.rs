@@ -1,3 +1,5 @@
 pub mod ast;
 pub mod node;
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod resolver;
@@ -0,0 +1,158 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::error::{ArityError, IndexError, Result};
+use crate::parser::resolver::Resolver;
+use crate::vm::{
+    bytecode::{Op, Origin},
+    compiler::Compiler,
+};
+use crate::ProgramState;
+
+use super::{
+    expression::Expr,
+    primitive::{DictKey, Primitive},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Builtin {
+    Len,
+    Push,
+    Pop,
+    Has,
+    Remove,
+    Keys,
+}
+
+impl Builtin {
+    //builtins are resolved by name at parse time so they take precedence over user functions
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "len" => Some(Self::Len),
+            "push" => Some(Self::Push),
+            "pop" => Some(Self::Pop),
+            "has" => Some(Self::Has),
+            "remove" => Some(Self::Remove),
+            "keys" => Some(Self::Keys),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Len => "len",
+            Self::Push => "push",
+            Self::Pop => "pop",
+            Self::Has => "has",
+            Self::Remove => "remove",
+            Self::Keys => "keys",
+        }
+    }
+    pub fn arity(&self) -> usize {
+        match self {
+            Self::Len => 1,
+            Self::Push => 2,
+            Self::Pop => 1,
+            Self::Has => 2,
+            Self::Remove => 2,
+            Self::Keys => 1,
+        }
+    }
+    pub fn check_arity(&self, found: usize) -> Result<()> {
+        if self.arity() != found {
+            return Err(ArityError {
+                expected: self.arity(),
+                found,
+            }
+            .into());
+        }
+        Ok(())
+    }
+    pub fn call(&self, args: &[Primitive]) -> Result<Primitive> {
+        match self {
+            Builtin::Len => match &args[0] {
+                Primitive::String(v) => Ok(Primitive::Integer(v.chars().count() as i64)),
+                Primitive::Dict(v) => Ok(Primitive::Integer(v.borrow().len() as i64)),
+                v => Ok(Primitive::Integer(v.as_array()?.borrow().len() as i64)),
+            },
+            Builtin::Push => {
+                args[0].as_array()?.borrow_mut().push(args[1].clone());
+                Ok(Primitive::VOID)
+            }
+            Builtin::Pop => args[0]
+                .as_array()?
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| IndexError::EmptyArray.into()),
+            Builtin::Has => Ok(Primitive::from(
+                args[0].as_dict()?.borrow().contains_key(&args[1].as_key()?),
+            )),
+            Builtin::Remove => {
+                let key = args[1].as_key()?;
+                args[0]
+                    .as_dict()?
+                    .borrow_mut()
+                    .remove(&key)
+                    .ok_or_else(|| IndexError::KeyNotFound(key.to_string()).into())
+            }
+            //keys come back sorted, ints before strings, so iteration order is deterministic
+            Builtin::Keys => {
+                let mut keys = args[0]
+                    .as_dict()?
+                    .borrow()
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<DictKey>>();
+                keys.sort_unstable();
+                Ok(Primitive::Array(Rc::new(RefCell::new(
+                    keys.into_iter().map(Primitive::from).collect(),
+                ))))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BuiltinCall {
+    builtin: Builtin,
+    argument_input: Box<[Expr]>,
+}
+
+impl BuiltinCall {
+    pub fn new(builtin: Builtin, argument_input: Box<[Expr]>) -> Self {
+        Self {
+            builtin,
+            argument_input,
+        }
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        for v in self.argument_input.iter() {
+            v.resolve(resolver);
+        }
+    }
+    pub fn builtin(&self) -> Builtin {
+        self.builtin
+    }
+    pub fn name(&self) -> &'static str {
+        self.builtin.name()
+    }
+    pub fn execute(&self, program: &ProgramState) -> Result<Primitive> {
+        self.builtin.check_arity(self.argument_input.len())?;
+        let args = self
+            .argument_input
+            .iter()
+            .map(|v| v.execute(program))
+            .collect::<Result<Vec<Primitive>>>()?;
+        self.builtin.call(&args)
+    }
+    //with the wrong number of arguments the call fails before any of them is evaluated
+    pub fn compile(&self, compiler: &mut Compiler, origin: Origin) {
+        if self.builtin.arity() == self.argument_input.len() {
+            for v in self.argument_input.iter() {
+                v.compile(compiler);
+            }
+        }
+        compiler.emit(
+            Op::Builtin(self.builtin, self.argument_input.len()),
+            Some(origin),
+        );
+    }
+}
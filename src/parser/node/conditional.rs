@@ -1,10 +1,15 @@
+use std::{cmp::Ordering, rc::Rc};
+
 use crate::ProgramState;
 
 use super::{
     expression::Expr,
+    primitive::Primitive,
     scope::{FlowStatement, Scope},
 };
-use anyhow::Result;
+use crate::error::{MatchError, Result};
+use crate::parser::resolver::Resolver;
+use crate::vm::{bytecode::Op, compiler::Compiler};
 
 #[derive(Debug)]
 pub struct IfElse {
@@ -22,8 +27,11 @@ impl IfElse {
     pub fn execute(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
         let mut flow_statement: Option<FlowStatement> = None;
         for (expr, scope) in self.if_clause.iter() {
-            let expr_val = expr.execute(program)?;
-            if expr_val.as_bool()? {
+            if expr
+                .execute(program)?
+                .into_condition(program.options.strict)
+                .map_err(|e| expr.locate(e, program))?
+            {
                 flow_statement = scope.execute(program, false)?;
                 return Ok(flow_statement);
             }
@@ -33,4 +41,171 @@ impl IfElse {
         }
         Ok(flow_statement)
     }
+    //an `if` used as a value, the parser makes sure it has an `else`. Not inlined for the same
+    //reason as `Scope::evaluate`
+    #[inline(never)]
+    pub fn evaluate(&self, program: &ProgramState) -> Result<Primitive> {
+        for (expr, scope) in self.if_clause.iter() {
+            let expr_val = expr.execute(program)?;
+            if expr_val
+                .as_condition(program.options.strict)
+                .map_err(|e| expr.locate(e, program))?
+            {
+                return scope.evaluate(program);
+            }
+        }
+        self.else_clause.as_ref().unwrap().evaluate(program)
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        for (expr, scope) in self.if_clause.iter() {
+            expr.resolve(resolver);
+            scope.resolve(resolver, false);
+        }
+        if let Some(scope) = &self.else_clause {
+            scope.resolve(resolver, false);
+        }
+    }
+    pub fn compile(&self, compiler: &mut Compiler) {
+        let mut ends = Vec::new();
+        for (expr, scope) in self.if_clause.iter() {
+            expr.compile(compiler);
+            let next = compiler.emit(Op::JumpIfFalse(0), Some(expr.origin(compiler.expr_pool())));
+            scope.compile(compiler, false);
+            ends.push(compiler.emit(Op::Jump(0), None));
+            compiler.patch(next);
+        }
+        if let Some(scope) = &self.else_clause {
+            scope.compile(compiler, false);
+        }
+        for end in ends {
+            compiler.patch(end);
+        }
+    }
+}
+
+//patterns compare ints and bools by value and never convert between them, even outside of strict mode
+#[derive(Debug)]
+pub enum Pattern {
+    Value(Primitive),
+    //ints, the flag is whether the range includes its end
+    Range(Primitive, Primitive, bool),
+    Wildcard,
+}
+
+fn compare_ints(a: &Primitive, b: &Primitive) -> Option<Ordering> {
+    match (a, b) {
+        (Primitive::Integer(a), Primitive::Integer(b)) => Some(a.cmp(b)),
+        _ if a.is_int() && b.is_int() => Some(a.as_big_int().ok()?.cmp(&b.as_big_int().ok()?)),
+        _ => None,
+    }
+}
+
+impl Pattern {
+    pub fn matches(&self, val: &Primitive) -> bool {
+        match self {
+            Pattern::Value(Primitive::Boolean(u)) => matches!(val, Primitive::Boolean(v) if u == v),
+            Pattern::Value(u) => compare_ints(val, u) == Some(Ordering::Equal),
+            Pattern::Range(start, end, inclusive) => {
+                compare_ints(val, start).is_some_and(|v| v.is_ge())
+                    && match compare_ints(val, end) {
+                        Some(Ordering::Less) => true,
+                        Some(Ordering::Equal) => *inclusive,
+                        _ => false,
+                    }
+            }
+            Pattern::Wildcard => true,
+        }
+    }
+}
+
+//the error for a value no arm accepts
+pub fn no_match(val: &Primitive) -> MatchError {
+    MatchError {
+        value: val.quoted(),
+    }
+}
+
+#[derive(Debug)]
+pub struct MatchArm {
+    //the arm is taken when one of the patterns matches and the guard holds
+    patterns: Rc<[Pattern]>,
+    guard: Option<Expr>,
+    scope: Scope,
+}
+
+impl MatchArm {
+    pub fn new(patterns: Rc<[Pattern]>, guard: Option<Expr>, scope: Scope) -> Self {
+        Self {
+            patterns,
+            guard,
+            scope,
+        }
+    }
+}
+
+//the first arm accepting the value runs, a value no arm accepts is an error
+#[derive(Debug)]
+pub struct Match {
+    expr: Expr,
+    arms: Box<[MatchArm]>,
+}
+
+impl Match {
+    pub fn new(expr: Expr, arms: Box<[MatchArm]>) -> Self {
+        Self { expr, arms }
+    }
+    pub fn execute(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
+        let val = self.expr.execute(program)?;
+        for arm in self.arms.iter() {
+            if !arm.patterns.iter().any(|v| v.matches(&val)) {
+                continue;
+            }
+            if let Some(guard) = &arm.guard {
+                let guard_val = guard.execute(program)?;
+                if !guard_val
+                    .as_condition(program.options.strict)
+                    .map_err(|e| guard.locate(e, program))?
+                {
+                    continue;
+                }
+            }
+            return arm.scope.execute(program, false);
+        }
+        Err(self.expr.locate(no_match(&val).into(), program))
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.expr.resolve(resolver);
+        for arm in self.arms.iter() {
+            if let Some(guard) = &arm.guard {
+                guard.resolve(resolver);
+            }
+            arm.scope.resolve(resolver, false);
+        }
+    }
+    //the value stays on the stack while the arms are tried and is popped before the body of
+    //the arm taken runs, so flow statements leave nothing behind
+    pub fn compile(&self, compiler: &mut Compiler) {
+        self.expr.compile(compiler);
+        let mut ends = Vec::new();
+        for arm in self.arms.iter() {
+            let patterns = compiler.add_patterns(Rc::clone(&arm.patterns));
+            let mut next = vec![compiler.emit(Op::JumpIfNoMatch(patterns, 0), None)];
+            if let Some(guard) = &arm.guard {
+                guard.compile(compiler);
+                let origin = guard.origin(compiler.expr_pool());
+                next.push(compiler.emit(Op::JumpIfFalse(0), Some(origin)));
+            }
+            compiler.emit(Op::Pop, None);
+            arm.scope.compile(compiler, false);
+            ends.push(compiler.emit(Op::Jump(0), None));
+            for at in next {
+                compiler.patch(at);
+            }
+        }
+        let origin = self.expr.origin(compiler.expr_pool());
+        compiler.emit(Op::NoMatch, Some(origin));
+        for end in ends {
+            compiler.patch(end);
+        }
+    }
 }
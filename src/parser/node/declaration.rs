@@ -1,13 +1,17 @@
-use anyhow::Ok;
-use anyhow::Result;
+use crate::error::{NameError, Result};
 
-use crate::environment::environment::Var;
+use crate::environment::environment::{Var, Variable};
+use crate::parser::resolver::Resolver;
+use crate::vm::{
+    bytecode::{Op, Origin},
+    compiler::Compiler,
+};
 use crate::ProgramState;
 
 use super::expression::Expr;
 use super::expression::OpType;
-use super::function::Func;
-use std::rc::Rc;
+use super::primitive::Primitive;
+use std::{cell::Cell, rc::Rc};
 
 #[derive(Debug)]
 pub enum AssignOperation {
@@ -16,69 +20,224 @@ pub enum AssignOperation {
     AssignSub,
     AssignMul,
     AssignDiv,
+    AssignShiftLeft,
+    AssignShiftRight,
+    AssignBitAnd,
+    AssignBitOr,
+    AssignBitXor,
+}
+
+impl AssignOperation {
+    fn op_type(&self) -> Option<OpType> {
+        match self {
+            Self::AssignOp => None,
+            Self::AssignAdd => Some(OpType::Add),
+            Self::AssignSub => Some(OpType::Sub),
+            Self::AssignMul => Some(OpType::Mul),
+            Self::AssignDiv => Some(OpType::Div),
+            Self::AssignShiftLeft => Some(OpType::ShiftLeft),
+            Self::AssignShiftRight => Some(OpType::ShiftRight),
+            Self::AssignBitAnd => Some(OpType::BitAnd),
+            Self::AssignBitOr => Some(OpType::BitOr),
+            Self::AssignBitXor => Some(OpType::BitXor),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Accessor {
+    Index(Expr),
+    Field(Rc<str>),
+}
+
+impl Accessor {
+    fn get(&self, container: &Primitive, program: &ProgramState) -> Result<Primitive> {
+        match self {
+            Self::Index(v) => container.index(&v.execute(program)?),
+            Self::Field(v) => container.as_struct()?.get_field(v),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Assignment {
-    var: Var,
+    var: Variable,
+    path: Box<[Accessor]>,
     op: AssignOperation,
     expr: Expr,
 }
 
 impl Assignment {
-    pub fn new(var: Var, op: AssignOperation, expr: Expr) -> Self {
-        Self { var, op, expr }
+    pub fn new(var: Var, path: Box<[Accessor]>, op: AssignOperation, expr: Expr) -> Self {
+        Self {
+            var: Variable::new(var),
+            path,
+            op,
+            expr,
+        }
+    }
+    pub fn var(&self) -> Var {
+        self.var.var()
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.expr.resolve(resolver);
+        for accessor in self.path.iter() {
+            if let Accessor::Index(index) = accessor {
+                index.resolve(resolver);
+            }
+        }
+        self.var.set_address(resolver.lookup(self.var.var()));
     }
     pub fn execute(&self, program: &ProgramState) -> Result<()> {
-        let val = program.environment.borrow().get_var(self.var)?;
-        let expr_val = match self.op {
-            AssignOperation::AssignAdd => {
-                let v = self.expr.execute(program)?;
-                v.evaluate_primary(&val, &OpType::Add)
+        let address = self.var.address();
+        let v = self.expr.execute(program)?;
+        let Some((last, path)) = self.path.split_last() else {
+            let mut environment = program.environment.borrow_mut();
+            return match self.op.op_type() {
+                Some(op) => {
+                    let res = environment.update_var(address, &v, &op, program.options.strict);
+                    v.release();
+                    res
+                }
+                None => environment.assign_var(address, v),
+            };
+        };
+        //every accessor but the last one only selects the container that is written to
+        let mut container = program.environment.borrow().get_var(address)?;
+        for accessor in path.iter() {
+            container = accessor.get(&container, program)?;
+        }
+        match last {
+            Accessor::Index(index) => {
+                let index = index.execute(program)?;
+                let expr_val = match self.op.op_type() {
+                    Some(op) => {
+                        container
+                            .index(&index)?
+                            .evaluate_binary(&v, &op, program.options.strict)?
+                    }
+                    None => v,
+                };
+                container.set_index(&index, expr_val)
             }
-            AssignOperation::AssignSub => {
-                let v = self.expr.execute(program)?;
-                v.evaluate_primary(&val, &OpType::Sub)
+            Accessor::Field(field) => {
+                let container = container.as_struct()?;
+                let expr_val = match self.op.op_type() {
+                    Some(op) => container.get_field(field)?.evaluate_binary(
+                        &v,
+                        &op,
+                        program.options.strict,
+                    )?,
+                    None => v,
+                };
+                container.set_field(field, expr_val)
             }
-            AssignOperation::AssignMul => {
-                let v = self.expr.execute(program)?;
-                v.evaluate_primary(&val, &OpType::Mul)
+        }
+    }
+    pub fn compile(&self, compiler: &mut Compiler, origin: Origin) {
+        self.expr.compile(compiler);
+        let Some((last, path)) = self.path.split_last() else {
+            compiler.emit(
+                Op::Assign(self.var.address(), self.op.op_type()),
+                Some(origin),
+            );
+            return;
+        };
+        compiler.emit(Op::GetVar(self.var.address()), Some(origin));
+        for accessor in path.iter() {
+            match accessor {
+                Accessor::Index(index) => {
+                    index.compile(compiler);
+                    compiler.emit(Op::Index, Some(origin));
+                }
+                Accessor::Field(field) => {
+                    let field = compiler.add_name(field);
+                    compiler.emit(Op::Field(field), Some(origin));
+                }
             }
-            AssignOperation::AssignDiv => {
-                let v = self.expr.execute(program)?;
-                v.evaluate_primary(&val, &OpType::Div)
+        }
+        match last {
+            Accessor::Index(index) => {
+                index.compile(compiler);
+                compiler.emit(Op::SetIndex(self.op.op_type()), Some(origin));
             }
-            AssignOperation::AssignOp => {
-                let v = self.expr.execute(program)?;
-                Ok(v)
+            Accessor::Field(field) => {
+                let field = compiler.add_name(field);
+                compiler.emit(Op::SetField(field, self.op.op_type()), Some(origin));
             }
-        }?;
-        program
-            .environment
-            .borrow_mut()
-            .assign_var(self.var, expr_val)
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum Declaration {
-    Expression { var: Var, expr: Expr },
-    Function { var: Var, func: Rc<Func> },
+    Expression {
+        var: Variable,
+        expr: Expr,
+        //set by the resolver when the block already declares the variable
+        redeclared: Cell<bool>,
+    },
 }
 impl Declaration {
+    pub fn new(var: Var, expr: Expr) -> Self {
+        Self::Expression {
+            var: Variable::new(var),
+            expr,
+            redeclared: Cell::new(false),
+        }
+    }
+    pub fn var(&self) -> Var {
+        match self {
+            Self::Expression { var, .. } => var.var(),
+        }
+    }
+    //the value is resolved before the variable is declared, so it refers to the variable it shadows
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        match self {
+            Self::Expression {
+                var,
+                expr,
+                redeclared,
+            } => {
+                expr.resolve(resolver);
+                let (address, exists) = resolver.declare(var.var());
+                var.set_address(address);
+                redeclared.set(exists);
+            }
+        }
+    }
     pub fn execute(&self, program: &ProgramState) -> Result<()> {
         match self {
-            Self::Expression { var, expr } => {
+            Self::Expression {
+                var,
+                expr,
+                redeclared,
+            } => {
                 let expr_val = expr.execute(program)?;
+                if redeclared.get() {
+                    return Err(NameError::ReDeclaration.into());
+                }
                 program
                     .environment
                     .borrow_mut()
-                    .subscribe_var(*var, expr_val)
+                    .declare_var(var.address(), expr_val)
+            }
+        }
+    }
+    pub fn compile(&self, compiler: &mut Compiler, origin: Origin) {
+        match self {
+            Self::Expression {
+                var,
+                expr,
+                redeclared,
+            } => {
+                expr.compile(compiler);
+                let op = match redeclared.get() {
+                    true => Op::Redeclare,
+                    false => Op::Declare(var.address()),
+                };
+                compiler.emit(op, Some(origin));
             }
-            Self::Function { var, func } => program
-                .environment
-                .borrow_mut()
-                .subscribe_func(*var, Rc::clone(func)),
         }
     }
 }
@@ -0,0 +1,92 @@
+use std::rc::Rc;
+
+use crate::{
+    environment::environment::Variable,
+    error::{PenguinError, Result},
+    parser::resolver::Resolver,
+    vm::{bytecode::Op, compiler::Compiler},
+    ProgramState,
+};
+
+use super::{
+    primitive::Primitive,
+    scope::{FlowStatement, Scope},
+    structure::{StructDef, StructValue},
+};
+
+//what a `catch` gets for an error of the interpreter
+pub fn error_value(e: &PenguinError) -> Primitive {
+    let def = StructDef::new(
+        "Error".into(),
+        ["kind", "code", "message"].map(Rc::from).into(),
+    );
+    let fields = [e.kind().into(), e.code().into(), e.message().into()];
+    Primitive::Struct(Rc::new(StructValue::new(Rc::new(def), Box::new(fields))))
+}
+
+//the `finally` runs however the `try` and the `catch` end. A flow statement or an error in it
+//replaces the one leaving the `try`, which is dropped
+#[derive(Debug)]
+pub struct Try {
+    body: Scope,
+    catch: Option<(Variable, Scope)>,
+    finally: Option<Scope>,
+}
+
+impl Try {
+    pub fn new(body: Scope, catch: Option<(Variable, Scope)>, finally: Option<Scope>) -> Self {
+        Self {
+            body,
+            catch,
+            finally,
+        }
+    }
+    pub fn execute(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
+        let mut flow_statement = self.body.execute(program, false);
+        if let Some((var, scope)) = &self.catch {
+            flow_statement = match flow_statement {
+                Err(e) if e.is_catchable() => {
+                    scope.execute_binding(program, var, program.catch(*e))
+                }
+                v => v,
+            };
+        }
+        if let Some(scope) = &self.finally {
+            if let Some(v) = scope.execute(program, false)? {
+                return Ok(Some(v));
+            }
+        }
+        flow_statement
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.body.resolve(resolver, false);
+        if let Some((var, scope)) = &self.catch {
+            scope.resolve_binding(resolver, var);
+        }
+        if let Some(scope) = &self.finally {
+            scope.resolve(resolver, false);
+        }
+    }
+    pub fn compile(&self, compiler: &mut Compiler) {
+        compiler.open_try(self.finally.is_some());
+        self.body.compile(compiler, false);
+        compiler.emit(Op::EndTry, None);
+        if let Some((var, scope)) = &self.catch {
+            let end = compiler.emit(Op::Jump(0), None);
+            //the error is on the stack
+            compiler.start_catch();
+            scope.compile_binding(compiler, var);
+            if self.finally.is_some() {
+                compiler.emit(Op::EndTry, None);
+            }
+            compiler.patch(end);
+        }
+        if let Some(scope) = &self.finally {
+            compiler.emit(Op::Finally, None);
+            compiler.start_finally();
+            scope.compile(compiler, false);
+            compiler.emit(Op::EndFinally, None);
+        }
+        compiler.close_try();
+    }
+}
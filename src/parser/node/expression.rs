@@ -1,9 +1,28 @@
-use crate::{environment::environment::Var, ProgramState};
-use anyhow::Result;
+use std::{cell::RefCell, rc::Rc};
 
-use super::{function::FunctionCall, primitive::Primitive};
+use crate::{
+    environment::environment::Variable,
+    error::{Location, PenguinError, Result},
+    parser::resolver::Resolver,
+    vm::{
+        bytecode::{Name, Op, Origin},
+        compiler::Compiler,
+    },
+    ProgramState,
+};
+use fxhash::FxHashMap;
+use pest::Span;
 
-#[derive(Debug)]
+use super::{
+    builtin::BuiltinCall,
+    conditional::IfElse,
+    function::{compile_call, resolve_call, ArgumentType, Closure, Func, FunctionCall},
+    primitive::Primitive,
+    scope::Scope,
+    structure::StructInit,
+};
+
+#[derive(Debug, Clone, Copy)]
 pub enum OpType {
     Add,
     Sub,
@@ -27,12 +46,207 @@ pub enum OpType {
     BitNot,
 }
 
+impl OpType {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            OpType::Add => "+",
+            OpType::Sub => "-",
+            OpType::Mul => "*",
+            OpType::Div => "/",
+            OpType::And => "&&",
+            OpType::Or => "||",
+            OpType::Opp => "!",
+            OpType::Gt => ">",
+            OpType::Lt => "<",
+            OpType::Gte => ">=",
+            OpType::Lte => "<=",
+            OpType::Eq => "==",
+            OpType::Neq => "!=",
+            OpType::Mod => "%",
+            OpType::ShiftLeft => "<<",
+            OpType::ShiftRight => ">>",
+            OpType::BitAnd => "&",
+            OpType::BitOr => "|",
+            OpType::BitXor => "^",
+            OpType::BitNot => "~",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Expr(pub usize);
 
 impl Expr {
+    //constants and variables are most of the nodes, they are read here instead of in a call
+    #[inline(always)]
     pub fn execute(&self, program: &ProgramState) -> Result<Primitive> {
-        program.expr_pool.pool[self.0].execute(program)
+        match &program.expr_pool.pool[self.0] {
+            Expression::Literal {
+                lhs: ExprAtom::Primitive(val),
+            } => Ok(val.clone()),
+            Expression::Literal {
+                lhs: ExprAtom::Var(val),
+            } => program.environment.borrow().get_var(val.address()),
+            expression => expression.execute(program),
+        }
+        .map_err(|e| self.locate(e, program))
+    }
+    pub fn locate(&self, e: Box<PenguinError>, program: &ProgramState) -> Box<PenguinError> {
+        self.origin(&program.expr_pool)
+            .locate(e, &program.environment.borrow())
+    }
+    //variables and calls report their errors on their name
+    pub fn origin(&self, expr_pool: &ExpressionPool) -> Origin {
+        let name = match &expr_pool.pool[self.0] {
+            Expression::Literal {
+                lhs: ExprAtom::Var(v),
+            } => Some(Name::Var(v.var())),
+            Expression::Literal {
+                lhs: ExprAtom::FunctionCall(v),
+            } => Some(Name::Var(v.var())),
+            Expression::Literal {
+                lhs: ExprAtom::BuiltinCall(v),
+            } => Some(Name::Builtin(v.builtin())),
+            _ => None,
+        };
+        Origin::new(expr_pool.location[self.0], name)
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        match &resolver.expr_pool().pool[self.0] {
+            Expression::Literal { lhs } => match lhs {
+                ExprAtom::Primitive(_) => {}
+                ExprAtom::FunctionCall(val) => val.resolve(resolver),
+                ExprAtom::BuiltinCall(val) => val.resolve(resolver),
+                ExprAtom::Var(val) => val.set_address(resolver.lookup(val.var())),
+                ExprAtom::Array(val) => {
+                    for v in val.iter() {
+                        v.resolve(resolver);
+                    }
+                }
+                ExprAtom::Dict(val) => {
+                    for (key, v) in val.iter() {
+                        key.resolve(resolver);
+                        v.resolve(resolver);
+                    }
+                }
+                ExprAtom::Struct(val) => {
+                    for v in val.fields() {
+                        v.resolve(resolver);
+                    }
+                }
+                ExprAtom::Function(val) => val.resolve(resolver),
+                ExprAtom::IfElse(val) => val.resolve(resolver),
+                ExprAtom::Block(val) => val.resolve(resolver, false),
+            },
+            Expression::Call {
+                lhs,
+                argument_input,
+            } => {
+                lhs.resolve(resolver);
+                resolve_call(argument_input, resolver);
+            }
+            Expression::Field { lhs, .. }
+            | Expression::Unary { lhs, .. }
+            | Expression::Chain { lhs, .. } => lhs.resolve(resolver),
+            Expression::Index { lhs, index } => {
+                lhs.resolve(resolver);
+                index.resolve(resolver);
+            }
+            Expression::Binary { lhs, rhs, .. } => {
+                lhs.resolve(resolver);
+                rhs.resolve(resolver);
+            }
+        }
+    }
+    pub fn compile(&self, compiler: &mut Compiler) {
+        let origin = self.origin(compiler.expr_pool());
+        match &compiler.expr_pool().pool[self.0] {
+            Expression::Literal { lhs } => match lhs {
+                ExprAtom::Primitive(val) => compiler.emit_constant(val.clone()),
+                ExprAtom::FunctionCall(val) => val.compile(compiler, origin),
+                ExprAtom::BuiltinCall(val) => val.compile(compiler, origin),
+                ExprAtom::Var(val) => {
+                    compiler.emit(Op::GetVar(val.address()), Some(origin));
+                }
+                ExprAtom::Array(val) => {
+                    for v in val.iter() {
+                        v.compile(compiler);
+                    }
+                    compiler.emit(Op::Array(val.len()), Some(origin));
+                }
+                ExprAtom::Dict(val) => {
+                    for (key, v) in val.iter() {
+                        key.compile(compiler);
+                        v.compile(compiler);
+                    }
+                    compiler.emit(Op::Dict(val.len()), Some(origin));
+                }
+                ExprAtom::Struct(val) => {
+                    for v in val.fields() {
+                        v.compile(compiler);
+                    }
+                    let init = compiler.add_struct(Rc::clone(val));
+                    compiler.emit(Op::Struct(init), Some(origin));
+                }
+                ExprAtom::Function(val) => {
+                    val.compile(compiler.expr_pool());
+                    let func = compiler.add_function(Rc::clone(val));
+                    compiler.emit(Op::Closure(func), Some(origin));
+                }
+                ExprAtom::IfElse(val) => val.compile(compiler),
+                ExprAtom::Block(val) => val.compile(compiler, false),
+            },
+            Expression::Call {
+                lhs,
+                argument_input,
+            } => {
+                lhs.compile(compiler);
+                compile_call(argument_input, compiler, origin);
+            }
+            Expression::Field { lhs, field } => {
+                lhs.compile(compiler);
+                let field = compiler.add_name(field);
+                compiler.emit(Op::Field(field), Some(origin));
+            }
+            Expression::Index { lhs, index } => {
+                lhs.compile(compiler);
+                index.compile(compiler);
+                compiler.emit(Op::Index, Some(origin));
+            }
+            Expression::Unary { lhs, op } => {
+                lhs.compile(compiler);
+                compiler.emit(Op::Unary(*op), Some(origin));
+            }
+            Expression::Chain { lhs, chain } => {
+                lhs.compile(compiler);
+                let chain = compiler.add_chain(Rc::clone(chain));
+                compiler.emit(Op::Chain(chain), Some(origin));
+            }
+            Expression::Binary { lhs, op, rhs } => {
+                lhs.compile(compiler);
+                //a constant operand is read by the op itself instead of going through the stack
+                if let Expression::Literal {
+                    lhs: ExprAtom::Primitive(val),
+                } = &compiler.expr_pool().pool[rhs.0]
+                {
+                    if !matches!(op, OpType::And | OpType::Or) {
+                        let constant = compiler.add_constant(val.clone());
+                        compiler.emit(Op::BinaryConstant(*op, constant), Some(origin));
+                        return;
+                    }
+                }
+                let jump = match op {
+                    OpType::And => Some(compiler.emit(Op::And(0), Some(origin))),
+                    OpType::Or => Some(compiler.emit(Op::Or(0), Some(origin))),
+                    _ => None,
+                };
+                rhs.compile(compiler);
+                compiler.emit(Op::Binary(*op), Some(origin));
+                if let Some(jump) = jump {
+                    compiler.patch(jump);
+                }
+            }
+        }
     }
 }
 
@@ -40,11 +254,26 @@ impl Expr {
 pub enum ExprAtom {
     Primitive(Primitive),
     FunctionCall(FunctionCall),
-    Var(Var),
+    BuiltinCall(BuiltinCall),
+    Var(Variable),
+    Array(Box<[Expr]>),
+    Dict(Box<[(Expr, Expr)]>),
+    Struct(Rc<StructInit>),
+    Function(Rc<Func>),
+    //`if` and blocks used as values
+    IfElse(Box<IfElse>),
+    Block(Box<Scope>),
 }
 #[derive(Debug)]
 pub struct ExpressionPool {
     pool: Vec<Expression>,
+    //where each expression comes from, only read when an error has to be reported
+    location: Vec<Location>,
+    //where the code being parsed starts in the whole source, code added to a running program
+    //(e.g. a REPL entry) comes after everything parsed before it
+    offset: usize,
+    //expressions before it have been folded already
+    folded: usize,
 }
 
 impl Default for ExpressionPool {
@@ -57,15 +286,29 @@ impl ExpressionPool {
     pub fn new() -> Self {
         ExpressionPool {
             pool: Vec::with_capacity(65536), // 2^16
+            location: Vec::with_capacity(65536),
+            offset: 0,
+            folded: 0,
         }
     }
     pub fn shrink(&mut self) {
         self.pool.shrink_to_fit();
+        self.location.shrink_to_fit();
     }
-    pub fn add(&mut self, expr: Expression) -> usize {
+    pub fn add(&mut self, expr: Expression, location: Location) -> usize {
         self.pool.push(expr);
+        self.location.push(location);
         self.pool.len() - 1
     }
+    pub fn location(&self, index: usize) -> Location {
+        self.location[index]
+    }
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+    pub fn span_location(&self, span: Span) -> Location {
+        Location::new(self.offset + span.start(), self.offset + span.end())
+    }
     pub fn execute(program_state: &ProgramState) -> Result<Primitive> {
         program_state
             .expr_pool
@@ -74,49 +317,279 @@ impl ExpressionPool {
             .unwrap()
             .execute(program_state)
     }
+    //folds the operations on constants added since the last call into their value and the
+    //operations with constants into chains. An operation that fails is kept so it fails when it runs.
+    //operands are added before the expressions using them so they are folded first
+    pub fn fold(&mut self, strict: bool) {
+        for i in self.folded..self.pool.len() {
+            if let Some(expr) = self.fold_expression(&self.pool[i], strict) {
+                self.pool[i] = expr;
+            }
+        }
+        self.folded = self.pool.len();
+    }
+    fn fold_expression(&self, expr: &Expression, strict: bool) -> Option<Expression> {
+        let literal = |v| Expression::Literal {
+            lhs: ExprAtom::Primitive(v),
+        };
+        match expr {
+            Expression::Unary { lhs, op } => match (self.constant(lhs), op) {
+                (Some(v), _) => v.evaluate_unary(op, strict).ok().map(literal),
+                (None, OpType::Opp) => match &self.pool[lhs.0] {
+                    Expression::Unary {
+                        lhs,
+                        op: OpType::Opp,
+                    } => Some(Expression::Chain {
+                        lhs: Expr(lhs.0),
+                        chain: Rc::new(Chain::new(
+                            vec![Step::Unary(OpType::Opp), Step::Unary(OpType::Opp)],
+                            None,
+                        )),
+                    }),
+                    _ => None,
+                },
+                (None, _) => None,
+            },
+            Expression::Binary { lhs, op, rhs } => match (self.constant(lhs), self.constant(rhs)) {
+                (Some(a), Some(b)) => Self::fold_binary(a, op, b, strict).ok().map(literal),
+                (None, Some(b)) if Chain::combines(op, false) && b.is_int() => {
+                    self.extend_chain(lhs, Step::Binary(*op, b.clone(), false))
+                }
+                (Some(a), None) if Chain::combines(op, true) && a.is_int() => {
+                    self.extend_chain(rhs, Step::Binary(*op, a.clone(), true))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    fn fold_binary(a: &Primitive, op: &OpType, b: &Primitive, strict: bool) -> Result<Primitive> {
+        match op {
+            OpType::And if !a.as_condition(strict)? => Ok(a.clone()),
+            OpType::Or if a.as_condition(strict)? => Ok(a.clone()),
+            _ => a.evaluate_binary(b, op, strict),
+        }
+    }
+    //`x * 2 * 4` is folded into one chain as long as the steps combine together
+    fn extend_chain(&self, lhs: &Expr, step: Step) -> Option<Expression> {
+        let (lhs, mut steps) = match &self.pool[lhs.0] {
+            Expression::Chain { lhs, chain } if chain.accepts(&step) => {
+                (Expr(lhs.0), chain.steps.to_vec())
+            }
+            _ => (Expr(lhs.0), Vec::new()),
+        };
+        steps.push(step);
+        let simplified = Chain::simplify(&steps).ok()?;
+        Some(Expression::Chain {
+            lhs,
+            chain: Rc::new(Chain::new(steps, simplified)),
+        })
+    }
+    fn constant(&self, expr: &Expr) -> Option<&Primitive> {
+        match &self.pool[expr.0] {
+            Expression::Literal {
+                lhs: ExprAtom::Primitive(v),
+            } => Some(v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Unary(OpType),
+    //an operation with a constant, which is the left hand side when the flag is set
+    Binary(OpType, Primitive, bool),
+}
+
+impl Step {
+    fn apply(&self, val: Primitive, strict: bool) -> Result<Primitive> {
+        match self {
+            Step::Unary(op) => val.evaluate_unary(op, strict),
+            Step::Binary(op, constant, false) => val.evaluate_binary(constant, op, strict),
+            Step::Binary(op, constant, true) => constant.evaluate_binary(&val, op, strict),
+        }
+    }
+}
+
+//operations applied one after the other to a value, like `x * 2 * 4` or `!!b`. The values the steps
+//are exact for (integers, or bools for `!`) go through `simplified` instead, a single operation or
+//none at all (`x + 0`). Other values go through every step as written so they keep their errors
+#[derive(Debug)]
+pub struct Chain {
+    steps: Box<[Step]>,
+    simplified: Option<Step>,
+}
+
+impl Chain {
+    fn new(steps: Vec<Step>, simplified: Option<Step>) -> Self {
+        Self {
+            steps: steps.into_boxed_slice(),
+            simplified,
+        }
+    }
+    //integers never overflow so sums and products of integers can be regrouped,
+    //`-` only when the constant is subtracted
+    fn combines(op: &OpType, swapped: bool) -> bool {
+        match op {
+            OpType::Add | OpType::Mul => true,
+            OpType::Sub => !swapped,
+            _ => false,
+        }
+    }
+    fn accepts(&self, step: &Step) -> bool {
+        let product = |v: &Step| matches!(v, Step::Binary(OpType::Mul, ..));
+        self.steps
+            .iter()
+            .all(|v| matches!(v, Step::Binary(..)) && product(v) == product(step))
+    }
+    fn simplify(steps: &[Step]) -> Result<Option<Step>> {
+        let (op, identity) = match steps[0] {
+            Step::Binary(OpType::Mul, ..) => (OpType::Mul, 1),
+            _ => (OpType::Add, 0),
+        };
+        let mut total = Primitive::Integer(identity);
+        for step in steps {
+            if let Step::Binary(step_op, constant, _) = step {
+                total = match step_op {
+                    OpType::Sub => total.evaluate_binary(constant, &OpType::Sub, false),
+                    _ => total.evaluate_binary(constant, &op, false),
+                }?;
+            }
+        }
+        Ok(match total {
+            Primitive::Integer(v) if v == identity => None,
+            _ => Some(Step::Binary(op, total, false)),
+        })
+    }
+    #[inline]
+    pub fn apply(&self, val: Primitive, strict: bool) -> Result<Primitive> {
+        let exact = match self.steps[0] {
+            Step::Unary(_) => matches!(val, Primitive::Boolean(_)),
+            Step::Binary(..) => val.is_int(),
+        };
+        if exact {
+            return match &self.simplified {
+                Some(step) => step.apply(val, strict),
+                None => Ok(val),
+            };
+        }
+        self.steps
+            .iter()
+            .try_fold(val, |val, step| step.apply(val, strict))
+    }
 }
 
 #[derive(Debug)]
 pub enum Expression {
-    Literal { lhs: ExprAtom },
-    Unary { lhs: Expr, op: OpType },
-    Binary { lhs: Expr, op: OpType, rhs: Expr },
+    Literal {
+        lhs: ExprAtom,
+    },
+    Unary {
+        lhs: Expr,
+        op: OpType,
+    },
+    Binary {
+        lhs: Expr,
+        op: OpType,
+        rhs: Expr,
+    },
+    //left by the folding of operations with constants
+    Chain {
+        lhs: Expr,
+        chain: Rc<Chain>,
+    },
+    Index {
+        lhs: Expr,
+        index: Expr,
+    },
+    Field {
+        lhs: Expr,
+        field: Rc<str>,
+    },
+    Call {
+        lhs: Expr,
+        argument_input: Box<[ArgumentType]>,
+    },
 }
 
 impl Expression {
     pub fn execute(&self, program: &ProgramState) -> Result<Primitive> {
         match self {
             Expression::Literal { lhs } => match lhs {
-                ExprAtom::Primitive(val) => Ok(*val),
+                ExprAtom::Primitive(val) => Ok(val.clone()),
 
                 ExprAtom::FunctionCall(val) => val.execute(program),
-                ExprAtom::Var(val) => program.environment.borrow_mut().get_var(*val),
+                ExprAtom::BuiltinCall(val) => val.execute(program),
+                ExprAtom::Var(val) => program.environment.borrow().get_var(val.address()),
+                ExprAtom::Array(val) => Ok(Primitive::Array(Rc::new(RefCell::new(
+                    val.iter()
+                        .map(|v| v.execute(program))
+                        .collect::<Result<Vec<Primitive>>>()?,
+                )))),
+                ExprAtom::Dict(val) => {
+                    let mut dict = FxHashMap::default();
+                    for (key, v) in val.iter() {
+                        dict.insert(key.execute(program)?.as_key()?, v.execute(program)?);
+                    }
+                    Ok(Primitive::Dict(Rc::new(RefCell::new(dict))))
+                }
+                ExprAtom::Struct(val) => val.execute(program),
+                ExprAtom::Function(val) => Ok(Primitive::Function(Rc::new(Closure::new(
+                    Rc::clone(val),
+                    program.environment.borrow().frame(),
+                )))),
+                ExprAtom::IfElse(val) => val.evaluate(program),
+                ExprAtom::Block(val) => val.evaluate(program),
             },
+            Expression::Call {
+                lhs,
+                argument_input,
+            } => {
+                let lhs_val = lhs.execute(program)?;
+                lhs_val.as_function()?.execute(argument_input, program)
+            }
+            Expression::Field { lhs, field } => {
+                let lhs_val = lhs.execute(program)?;
+                lhs_val.as_struct()?.get_field(field)
+            }
+            Expression::Index { lhs, index } => {
+                let lhs_val = lhs.execute(program)?;
+                let index_val = index.execute(program)?;
+                lhs_val.index(&index_val)
+            }
             Expression::Unary { lhs, op } => {
-                let lhs_val = program.expr_pool.pool[lhs.0].execute(program)?;
-                Ok(lhs_val.evaluate_unary(op)?)
+                let lhs_val = lhs.execute(program)?;
+                Ok(lhs_val.evaluate_unary(op, program.options.strict)?)
+            }
+            Expression::Chain { lhs, chain } => {
+                let lhs_val = lhs.execute(program)?;
+                chain.apply(lhs_val, program.options.strict)
             }
             Expression::Binary { lhs, op, rhs } => match op {
                 OpType::And => {
-                    let lhs_val = program.expr_pool.pool[lhs.0].execute(program)?;
-                    if !(lhs_val.as_bool()?) {
+                    let lhs_val = lhs.execute(program)?;
+                    if !(lhs_val.as_condition(program.options.strict)?) {
                         return Ok(lhs_val);
                     }
-                    let rhs_val = program.expr_pool.pool[rhs.0].execute(program)?;
-                    Ok(lhs_val.evaluate_primary(&rhs_val, op)?)
+                    let rhs_val = rhs.execute(program)?;
+                    Ok(lhs_val.evaluate_binary(&rhs_val, op, program.options.strict)?)
                 }
                 OpType::Or => {
-                    let lhs_val = program.expr_pool.pool[lhs.0].execute(program)?;
-                    if lhs_val.as_bool()? {
+                    let lhs_val = lhs.execute(program)?;
+                    if lhs_val.as_condition(program.options.strict)? {
                         return Ok(lhs_val);
                     }
-                    let rhs_val = program.expr_pool.pool[rhs.0].execute(program)?;
-                    Ok(lhs_val.evaluate_primary(&rhs_val, op)?)
+                    let rhs_val = rhs.execute(program)?;
+                    Ok(lhs_val.evaluate_binary(&rhs_val, op, program.options.strict)?)
                 }
                 _ => {
-                    let lhs_val = program.expr_pool.pool[lhs.0].execute(program)?;
-                    let rhs_val = program.expr_pool.pool[rhs.0].execute(program)?;
-                    Ok(lhs_val.evaluate_primary(&rhs_val, op)?)
+                    let lhs_val = lhs.execute(program)?;
+                    let rhs_val = rhs.execute(program)?;
+                    let val = lhs_val.evaluate_binary(&rhs_val, op, program.options.strict);
+                    lhs_val.release();
+                    rhs_val.release();
+                    val
                 }
             },
         }
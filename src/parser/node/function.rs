@@ -1,108 +1,303 @@
-use std::rc::Rc;
+use std::{
+    cell::{Cell, OnceCell},
+    fmt,
+    rc::Rc,
+};
 
-use crate::{environment::environment::Var, parser::node::scope::ScopeError, ProgramState};
-use anyhow::{anyhow, Result};
+use crate::{
+    environment::environment::{Address, Frame, Var, Variable},
+    error::{ArityError, ControlFlowError, Result},
+    parser::resolver::Resolver,
+    vm::{
+        bytecode::{Chunk, Op, Origin},
+        compiler::Compiler,
+    },
+    ProgramState,
+};
 
 use super::{
-    expression::Expr,
+    expression::{Expr, ExpressionPool},
+    native::NativeFunction,
     primitive::Primitive,
     scope::{FlowStatement, Scope},
 };
 
 #[derive(Debug)]
 pub enum ArgumentType {
-    Ref(Var),
-    Func(Func),
+    Ref(Variable),
     Expr(Expr),
 }
 
 #[derive(Debug)]
 pub struct Func {
     argument: Box<[Var]>,
+    //only kept to print function values
+    argument_name: Box<[Rc<str>]>,
     scope: Scope,
+    //size of the frame of a call, set by the resolver. The arguments take the first slots
+    slots: Cell<usize>,
+    //bytecode of the body, set when the function is compiled for the vm
+    chunk: OnceCell<Chunk>,
 }
 
 impl Func {
-    pub fn new(argument: Box<[Var]>, scope: Scope) -> Self {
-        Self { argument, scope }
+    pub fn new(argument: Box<[Var]>, argument_name: Box<[Rc<str>]>, scope: Scope) -> Self {
+        Self {
+            argument,
+            argument_name,
+            scope,
+            slots: Cell::new(0),
+            chunk: OnceCell::new(),
+        }
+    }
+    pub fn check_arity(&self, found: usize) -> Result<()> {
+        if self.argument.len() != found {
+            return Err(ArityError {
+                expected: self.argument.len(),
+                found,
+            }
+            .into());
+        }
+        Ok(())
     }
     pub fn execute(
-        self: Rc<Self>,
+        &self,
+        env: Rc<Frame>,
         argument_input: &[ArgumentType],
         program: &ProgramState,
     ) -> Result<Primitive> {
-        //only do 1 comparison here if it works
-        //todo: Move it to the compilation process
-        if self.argument.len() != argument_input.len() {
-            if self.argument.len() < argument_input.len() {
-                return Err(anyhow!("too many arguments in function call"));
+        self.check_arity(argument_input.len())?;
+        //arguments belong to the caller so they are evaluated before the function scope is opened
+        let argument_val = argument_input
+            .iter()
+            .map(|v| match v {
+                ArgumentType::Ref(val) => program.environment.borrow().get_var(val.address()),
+                ArgumentType::Expr(val) => val.execute(program),
+            })
+            .collect::<Result<Vec<Primitive>>>()?;
+        let refs = argument_input.iter().map(|v| match v {
+            ArgumentType::Ref(val) => Some(val.address()),
+            ArgumentType::Expr(_) => None,
+        });
+        self.call(env, argument_val, refs, program, || {
+            match self.scope.execute(program, true)? {
+                Some(FlowStatement::Break(_)) => Err(ControlFlowError::BreakOutsideLoop.into()),
+                Some(FlowStatement::Continue(_)) => {
+                    Err(ControlFlowError::ContinueOutsideLoop.into())
+                }
+                Some(FlowStatement::Return(v)) => Ok(v),
+                None => Ok(Primitive::VOID),
+            }
+        })
+    }
+    //runs `body` in a new frame whose parent is `env` and that holds the arguments. `refs` are the
+    //caller's variables passed by reference, they get the final value of their argument once the body is done
+    pub fn call(
+        &self,
+        env: Rc<Frame>,
+        argument_val: Vec<Primitive>,
+        refs: impl Iterator<Item = Option<Address>> + Clone,
+        program: &ProgramState,
+        body: impl FnOnce() -> Result<Primitive>,
+    ) -> Result<Primitive> {
+        program
+            .environment
+            .borrow_mut()
+            .open_call(env, self.slots.get())?;
+        let res = self.call_body(argument_val, refs.clone(), program, body);
+        program.environment.borrow_mut().close_call();
+        let (rt_val, ref_val) = res?;
+        for (address, v) in refs.zip(ref_val) {
+            if let (Some(address), Some(v)) = (address, v) {
+                program.environment.borrow_mut().assign_var(address, v)?
             }
-            return Err(anyhow!("too few arguments in function call"));
         }
-        program.environment.borrow_mut().open_function_scope();
-        for (i, v) in argument_input.iter().enumerate() {
-            match v {
-                ArgumentType::Func(val) => {
-                    todo!()
-                }
-                ArgumentType::Ref(val) => {
-                    let v = program.environment.borrow().get_ref(*val)?;
-                    program.environment.borrow_mut().subscribe_var(*val, v)?;
-                }
-                ArgumentType::Expr(val) => {
-                    let expr_val = val.execute(program)?;
-                    program
-                        .environment
-                        .borrow_mut()
-                        .subscribe_var(self.argument[i], expr_val)?
-                }
+        Ok(rt_val)
+    }
+    fn call_body(
+        &self,
+        argument_val: Vec<Primitive>,
+        refs: impl Iterator<Item = Option<Address>>,
+        program: &ProgramState,
+        body: impl FnOnce() -> Result<Primitive>,
+    ) -> Result<(Primitive, Vec<Option<Primitive>>)> {
+        for (slot, v) in argument_val.into_iter().enumerate() {
+            program
+                .environment
+                .borrow_mut()
+                .declare_var(Self::argument_address(slot), v)?;
+        }
+        let rt_val = body()?;
+        let ref_val = refs
+            .enumerate()
+            .map(|(slot, v)| match v {
+                Some(_) => program
+                    .environment
+                    .borrow()
+                    .get_var(Self::argument_address(slot))
+                    .map(Some),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<Option<Primitive>>>>()?;
+        Ok((rt_val, ref_val))
+    }
+    fn argument_address(slot: usize) -> Address {
+        Address::Local {
+            depth: 0,
+            slot: slot as u32,
+        }
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        let declared = self.argument.iter().copied();
+        resolver.open_block(declared.chain(self.scope.declarations()), true, true);
+        for var in self.argument.iter() {
+            resolver.declare(*var);
+        }
+        self.scope.resolve(resolver, true);
+        self.slots.set(resolver.close_block().unwrap());
+    }
+    pub fn compile(&self, expr_pool: &ExpressionPool) {
+        self.chunk.get_or_init(|| {
+            let mut compiler = Compiler::new(expr_pool);
+            self.scope.compile(&mut compiler, true);
+            compiler.finish()
+        });
+    }
+    pub fn chunk(&self) -> &Chunk {
+        self.chunk.get().unwrap()
+    }
+}
+
+pub fn resolve_call(argument_input: &[ArgumentType], resolver: &mut Resolver) {
+    for v in argument_input.iter() {
+        match v {
+            ArgumentType::Ref(val) => val.set_address(resolver.lookup(val.var())),
+            ArgumentType::Expr(val) => val.resolve(resolver),
+        }
+    }
+}
+
+//the arguments are compiled after the callee has been checked, for each argument the variable
+//passed by reference is kept so the call can write it back
+pub fn compile_call(argument_input: &[ArgumentType], compiler: &mut Compiler, origin: Origin) {
+    compiler.emit(Op::CheckCall(argument_input.len()), Some(origin));
+    let refs = argument_input
+        .iter()
+        .map(|v| match v {
+            ArgumentType::Ref(val) => {
+                compiler.emit(Op::GetVar(val.address()), Some(origin));
+                Some(val.address())
             }
+            ArgumentType::Expr(val) => {
+                val.compile(compiler);
+                None
+            }
+        })
+        .collect();
+    let call = compiler.add_call(refs);
+    compiler.emit(Op::Call(call), Some(origin));
+}
+
+//a function value, created every time a function literal is evaluated. It keeps the frame
+//it was created in so that the body resolves names from where it was written, not from where it is called.
+//variables are captured by reference: assigning to a captured variable changes it for the enclosing
+//function and for every other closure created in the same frame, and the frame stays alive as long as
+//one of its closures does
+pub enum Closure {
+    Script { func: Rc<Func>, env: Rc<Frame> },
+    //registered by the host program, it has no frame
+    Native(Rc<NativeFunction>),
+}
+
+impl Closure {
+    pub fn new(func: Rc<Func>, env: Rc<Frame>) -> Self {
+        Self::Script { func, env }
+    }
+    pub fn check_arity(&self, found: usize) -> Result<()> {
+        match self {
+            Self::Script { func, .. } => func.check_arity(found),
+            Self::Native(native) => native.check_arity(found),
         }
+    }
+    pub fn execute(
+        &self,
+        argument_input: &[ArgumentType],
+        program: &ProgramState,
+    ) -> Result<Primitive> {
+        match self {
+            Self::Script { func, env } => func.execute(Rc::clone(env), argument_input, program),
+            Self::Native(native) => {
+                native.check_arity(argument_input.len())?;
+                let argument_val = argument_input
+                    .iter()
+                    .map(|v| match v {
+                        ArgumentType::Ref(val) => {
+                            program.environment.borrow().get_var(val.address())
+                        }
+                        ArgumentType::Expr(val) => val.execute(program),
+                    })
+                    .collect::<Result<Vec<Primitive>>>()?;
+                native.call(&argument_val, program.options.strict)
+            }
+        }
+    }
+}
+
+//the frame is left out since it usually holds the closure itself
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Script { func, .. } => f.debug_struct("Closure").field("func", func).finish(),
+            Self::Native(native) => native.fmt(f),
+        }
+    }
+}
 
-        let flow_statement: Option<FlowStatement> = self.scope.execute(program, true)?;
-        let rt_val = match flow_statement {
-            Some(FlowStatement::Break) => Err(anyhow!(ScopeError::BreakOutsideLoop))?,
-            Some(FlowStatement::Continue) => Err(anyhow!(ScopeError::ContinueOutsideLoop))?,
-            Some(FlowStatement::Return(v)) => v,
-            None => Primitive::VOID,
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let func = match self {
+            Self::Script { func, .. } => func,
+            Self::Native(native) => return write!(f, "{}", native),
         };
-        let ref_val = argument_input
-            .iter()
-            .map(|v| match v {
-                ArgumentType::Ref(val) => program.environment.borrow().get_var(*val).ok(),
-                _ => None,
-            })
-            .collect::<Vec<Option<Primitive>>>();
-        program.environment.borrow_mut().close_function_scope();
-        for (i, v) in ref_val.into_iter().enumerate() {
-            if let Some(u) = v {
-                program
-                    .environment
-                    .borrow_mut()
-                    .assign_var(self.argument[i], u)?
+        write!(f, "(")?;
+        for (i, name) in func.argument_name.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
             }
+            write!(f, "{}", name)?;
         }
-        Ok(rt_val)
+        write!(f, ") => {{...}}")
     }
 }
 
 #[derive(Debug)]
 
 pub struct FunctionCall {
-    var: Var,
+    var: Variable,
     argument_input: Box<[ArgumentType]>,
 }
 
 impl FunctionCall {
     pub fn new(var: Var, argument_input: Box<[ArgumentType]>) -> Self {
         FunctionCall {
-            var,
+            var: Variable::new(var),
             argument_input,
         }
     }
+    pub fn var(&self) -> Var {
+        self.var.var()
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.var.set_address(resolver.lookup(self.var.var()));
+        resolve_call(&self.argument_input, resolver);
+    }
     pub fn execute(&self, program: &ProgramState) -> Result<Primitive> {
-        let func = program.environment.borrow().get_func(self.var)?;
-        let val = func.execute(&self.argument_input, program)?;
+        let func = program.environment.borrow().get_var(self.var.address())?;
+        let val = func.as_function()?.execute(&self.argument_input, program)?;
         Ok(val)
     }
+    pub fn compile(&self, compiler: &mut Compiler, origin: Origin) {
+        compiler.emit(Op::GetVar(self.var.address()), Some(origin));
+        compile_call(&self.argument_input, compiler, origin);
+    }
 }
@@ -1,7 +1,12 @@
 use crate::ProgramState;
 
 use super::expression::Expr;
-use anyhow::Result;
+use crate::error::Result;
+use crate::parser::resolver::Resolver;
+use crate::vm::{
+    bytecode::{Op, Origin},
+    compiler::Compiler,
+};
 
 #[derive(Debug)]
 pub struct Output {
@@ -15,7 +20,14 @@ impl Output {
     }
     pub fn execute(&self, program: &ProgramState) -> Result<()> {
         let expr_val = self.expr.execute(program)?;
-        print!("{}{}", expr_val, self.end);
-        Ok(())
+        program.print(&expr_val, &self.end)
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.expr.resolve(resolver);
+    }
+    pub fn compile(&self, compiler: &mut Compiler, origin: Origin) {
+        self.expr.compile(compiler);
+        let end = compiler.add_name(&self.end);
+        compiler.emit(Op::Print(end), Some(origin));
     }
 }
@@ -1,39 +1,209 @@
-use anyhow::Result;
+use crate::environment::environment::{Var, Variable};
+use crate::error::{ArithmeticError, Result, TypeError};
+use crate::parser::resolver::Resolver;
+use crate::vm::{bytecode::Op, compiler::Compiler};
 
 use crate::ProgramState;
 
 use super::{
     expression::Expr,
+    primitive::Primitive,
     scope::{FlowStatement, Scope},
 };
 
+//whether a loop labeled `label` handles a `break` or `continue` targeting `target`
+fn handles(label: Option<Var>, target: Option<Var>) -> bool {
+    target.is_none() || target == label
+}
+
 #[derive(Debug)]
 pub struct WhileLoop {
+    label: Option<Var>,
     expr: Expr,
     scope: Scope,
 }
 
 impl WhileLoop {
-    pub fn new(expr: Expr, scope: Scope) -> Self {
-        WhileLoop { expr, scope }
+    pub fn new(label: Option<Var>, expr: Expr, scope: Scope) -> Self {
+        WhileLoop { label, expr, scope }
+    }
+    pub fn execute(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
+        while self
+            .expr
+            .execute(program)?
+            .into_condition(program.options.strict)
+            .map_err(|e| self.expr.locate(e, program))?
+        {
+            match self.scope.execute(program, false)? {
+                Some(FlowStatement::Break(target)) if handles(self.label, target) => break,
+                Some(FlowStatement::Continue(target)) if handles(self.label, target) => {}
+                Some(flow) => return Ok(Some(flow)),
+                None => {}
+            }
+        }
+        Ok(None)
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.expr.resolve(resolver);
+        self.scope.resolve(resolver, false);
+    }
+    pub fn compile(&self, compiler: &mut Compiler) {
+        let start = compiler.position();
+        self.expr.compile(compiler);
+        let exit = compiler.emit(
+            Op::JumpIfFalse(0),
+            Some(self.expr.origin(compiler.expr_pool())),
+        );
+        compiler.open_loop(start, self.label, false);
+        self.scope.compile(compiler, false);
+        compiler.emit(Op::Jump(start), None);
+        compiler.patch(exit);
+        compiler.close_loop();
+    }
+}
+
+//the values a `for` loop goes through. The number of iterations is counted up front so that
+//a range ending at the largest int stops instead of overflowing
+#[derive(Debug)]
+pub struct Range {
+    next: i64,
+    step: i64,
+    remaining: u64,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, step: i64, inclusive: bool) -> Result<Self> {
+        if step == 0 {
+            return Err(ArithmeticError::ZeroStep.into());
+        }
+        let (start, end, step) = (start as i128, end as i128, step as i128);
+        //the last value the range may reach, a negative step goes down
+        let last = match inclusive {
+            true => end,
+            false => end - step.signum(),
+        };
+        let span = (last - start) * step.signum();
+        let remaining = match span < 0 {
+            true => 0,
+            false => span / step.abs() + 1,
+        };
+        Ok(Self {
+            next: start as i64,
+            step: step as i64,
+            remaining: remaining.min(u64::MAX as i128) as u64,
+        })
+    }
+}
+
+impl Iterator for Range {
+    type Item = i64;
+    #[inline]
+    fn next(&mut self) -> Option<i64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let v = self.next;
+        self.next = self.next.wrapping_add(self.step);
+        Some(v)
+    }
+}
+
+//the bounds and the step of a range are ints, bools only count as ints outside of strict mode
+pub fn range_bound(v: &Primitive, strict: bool) -> Result<i64> {
+    match v {
+        Primitive::Boolean(_) if strict => Err(TypeError::TypeMismatch {
+            expected: "int",
+            found: v.type_name(),
+        }
+        .into()),
+        _ => v.as_int(),
+    }
+}
+
+#[derive(Debug)]
+pub struct ForLoop {
+    label: Option<Var>,
+    var: Variable,
+    start: Expr,
+    end: Expr,
+    step: Option<Expr>,
+    inclusive: bool,
+    scope: Scope,
+}
+
+impl ForLoop {
+    pub fn new(
+        label: Option<Var>,
+        var: Var,
+        start: Expr,
+        end: Expr,
+        step: Option<Expr>,
+        inclusive: bool,
+        scope: Scope,
+    ) -> Self {
+        ForLoop {
+            label,
+            var: Variable::new(var),
+            start,
+            end,
+            step,
+            inclusive,
+            scope,
+        }
+    }
+    fn bound(expr: &Expr, program: &ProgramState) -> Result<i64> {
+        range_bound(&expr.execute(program)?, program.options.strict)
+            .map_err(|e| expr.locate(e, program))
     }
     pub fn execute(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
-        let mut expr_val = self.expr.execute(program)?;
-        let mut flow_statement: Option<FlowStatement> = None;
-        while expr_val.as_bool()? {
-            let v = self.scope.execute(program, false)?;
-            if let Some(flow) = v {
-                match flow {
-                    FlowStatement::Break => break,
-                    FlowStatement::Return(v) => {
-                        flow_statement = Some(FlowStatement::Return(v));
-                        break;
-                    }
-                    FlowStatement::Continue => {}
-                }
+        let start = Self::bound(&self.start, program)?;
+        let end = Self::bound(&self.end, program)?;
+        let range = match &self.step {
+            Some(step) => Range::new(start, end, Self::bound(step, program)?, self.inclusive)
+                .map_err(|e| step.locate(e, program))?,
+            None => Range::new(start, end, 1, self.inclusive)?,
+        };
+        for i in range {
+            match self
+                .scope
+                .execute_binding(program, &self.var, Primitive::Integer(i))?
+            {
+                Some(FlowStatement::Break(target)) if handles(self.label, target) => break,
+                Some(FlowStatement::Continue(target)) if handles(self.label, target) => {}
+                Some(flow) => return Ok(Some(flow)),
+                None => {}
             }
-            expr_val = self.expr.execute(program)?;
         }
-        Ok(flow_statement)
+        Ok(None)
+    }
+    pub fn resolve(&self, resolver: &mut Resolver) {
+        self.start.resolve(resolver);
+        self.end.resolve(resolver);
+        if let Some(step) = &self.step {
+            step.resolve(resolver);
+        }
+        self.scope.resolve_binding(resolver, &self.var);
+    }
+    //the range is kept by the vm while the loop runs, `ForNext` pushes its next value
+    //or leaves the loop once it is done
+    pub fn compile(&self, compiler: &mut Compiler) {
+        for expr in [Some(&self.start), Some(&self.end), self.step.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            expr.compile(compiler);
+            compiler.emit(Op::Bound, Some(expr.origin(compiler.expr_pool())));
+        }
+        let origin = self.step.as_ref().map(|v| v.origin(compiler.expr_pool()));
+        compiler.emit(Op::Range(self.inclusive, self.step.is_some()), origin);
+        let start = compiler.position();
+        let exit = compiler.emit(Op::ForNext(0, None), None);
+        compiler.open_loop(start, self.label, true);
+        self.scope.compile_binding(compiler, &self.var);
+        compiler.emit(Op::Jump(start), None);
+        compiler.patch(exit);
+        compiler.close_loop();
+        compiler.emit(Op::EndRange, None);
     }
 }
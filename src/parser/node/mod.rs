@@ -1,8 +1,12 @@
+pub mod builtin;
 pub mod conditional;
 pub mod declaration;
+pub mod exception;
 pub mod expression;
 pub mod function;
 pub mod io;
 pub mod looping;
+pub mod native;
 pub mod primitive;
 pub mod scope;
+pub mod structure;
@@ -0,0 +1,199 @@
+use std::{fmt, rc::Rc};
+
+use num_bigint::BigInt;
+
+use crate::error::{ArityError, NativeError, Result, TypeError};
+
+use super::primitive::Primitive;
+
+//the closure of a native function once its arguments and result are converted
+pub type NativeBody = Box<dyn Fn(&[Primitive], bool) -> Result<Primitive>>;
+
+//a function of the host program registered through the `Interpreter`, scripts call it like any other
+//function. Arguments passed by reference are given by value and are not written back
+pub struct NativeFunction {
+    name: Rc<str>,
+    arity: usize,
+    body: NativeBody,
+}
+
+impl NativeFunction {
+    pub fn new<Args>(name: &str, f: impl NativeFn<Args>) -> Self {
+        Self {
+            name: name.into(),
+            arity: f.arity(),
+            body: f.into_body(),
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn check_arity(&self, found: usize) -> Result<()> {
+        if self.arity != found {
+            return Err(ArityError {
+                expected: self.arity,
+                found,
+            }
+            .into());
+        }
+        Ok(())
+    }
+    pub fn call(&self, argument_val: &[Primitive], strict: bool) -> Result<Primitive> {
+        self.check_arity(argument_val.len())?;
+        (self.body)(argument_val, strict)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native {}>", self.name)
+    }
+}
+
+//the types a native function takes its arguments as. The conversions are the ones the
+//language does itself, e.g. a bool passed for an int is 0 or 1 unless `strict` is set
+pub trait FromPrimitive: Sized {
+    fn from_primitive(v: &Primitive, strict: bool) -> Result<Self>;
+}
+
+//strict mode does not turn bools into numbers
+fn reject_bool(v: &Primitive, expected: &'static str, strict: bool) -> Result<()> {
+    match v {
+        Primitive::Boolean(_) if strict => Err(TypeError::TypeMismatch {
+            expected,
+            found: v.type_name(),
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+impl FromPrimitive for Primitive {
+    fn from_primitive(v: &Primitive, _: bool) -> Result<Self> {
+        Ok(v.clone())
+    }
+}
+impl FromPrimitive for i64 {
+    fn from_primitive(v: &Primitive, strict: bool) -> Result<Self> {
+        reject_bool(v, "int", strict)?;
+        v.as_int()
+    }
+}
+impl FromPrimitive for BigInt {
+    fn from_primitive(v: &Primitive, strict: bool) -> Result<Self> {
+        reject_bool(v, "int", strict)?;
+        v.as_big_int()
+    }
+}
+impl FromPrimitive for f64 {
+    fn from_primitive(v: &Primitive, strict: bool) -> Result<Self> {
+        reject_bool(v, "float", strict)?;
+        v.as_float()
+    }
+}
+impl FromPrimitive for bool {
+    fn from_primitive(v: &Primitive, strict: bool) -> Result<Self> {
+        v.as_condition(strict)
+    }
+}
+impl FromPrimitive for Rc<str> {
+    fn from_primitive(v: &Primitive, _: bool) -> Result<Self> {
+        match v {
+            Primitive::String(v) => Ok(Rc::from(v.as_str())),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "string",
+                found: v.type_name(),
+            }
+            .into()),
+        }
+    }
+}
+impl FromPrimitive for String {
+    fn from_primitive(v: &Primitive, strict: bool) -> Result<Self> {
+        Rc::<str>::from_primitive(v, strict).map(|v| v.to_string())
+    }
+}
+
+//what a native function may return. `Err` fails the call with the message of the error
+pub trait NativeResult {
+    fn into_result(self) -> std::result::Result<Primitive, String>;
+}
+
+macro_rules! impl_native_result {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl NativeResult for $ty {
+                fn into_result(self) -> std::result::Result<Primitive, String> {
+                    Ok(self.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_native_result!(
+    Primitive,
+    i64,
+    BigInt,
+    f64,
+    bool,
+    Rc<str>,
+    String,
+    &'static str,
+    ()
+);
+
+impl<T: NativeResult, E: fmt::Display> NativeResult for std::result::Result<T, E> {
+    fn into_result(self) -> std::result::Result<Primitive, String> {
+        self.map_err(|e| e.to_string())?.into_result()
+    }
+}
+
+//closures taking up to six arguments that convert from `Primitive`. `Args` is the tuple of
+//the argument types, it only tells the implementations apart
+pub trait NativeFn<Args>: 'static {
+    fn arity(&self) -> usize;
+    fn into_body(self) -> NativeBody;
+}
+
+macro_rules! impl_native_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> NativeFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: NativeResult,
+            $($arg: FromPrimitive),*
+        {
+            fn arity(&self) -> usize {
+                <[&str]>::len(&[$(stringify!($arg)),*])
+            }
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn into_body(self) -> NativeBody {
+                Box::new(move |argument_val, strict| {
+                    let mut argument_val = argument_val.iter();
+                    $(let $arg = $arg::from_primitive(argument_val.next().unwrap(), strict)?;)*
+                    self($($arg),*)
+                        .into_result()
+                        .map_err(|message| NativeError { message }.into())
+                })
+            }
+        }
+    };
+}
+
+impl_native_fn!();
+impl_native_fn!(A);
+impl_native_fn!(A, B);
+impl_native_fn!(A, B, C);
+impl_native_fn!(A, B, C, D);
+impl_native_fn!(A, B, C, D, E);
+impl_native_fn!(A, B, C, D, E, G);
@@ -1,66 +1,820 @@
-use anyhow::Result;
+use crate::error::{ArithmeticError, IndexError, PenguinError, Result, TypeError};
 use core::fmt::Debug;
+use fxhash::FxHashMap;
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
 
-use super::expression::OpType;
+use super::{expression::OpType, function::Closure, structure::StructValue};
 
-#[derive(Debug, Clone, Copy)]
+//a word sized tag keeps every payload aligned, a byte tag made moving values around the
+//interpreter noticeably slower since the payload would sit at an unaligned offset
+#[derive(Debug)]
+#[repr(u64)]
 pub enum Primitive {
     Integer(i64),
-    Boolean(bool),
+    //integers that do not fit in an `i64`, results are demoted back to `Integer` as soon as they fit
+    //so both variants never hold the same value
+    BigInt(Rc<BigInt>),
+    Float(FloatWord),
+    Boolean(BoolWord),
+    String(Rc<String>),
+    Array(Rc<RefCell<Vec<Primitive>>>),
+    Dict(Rc<RefCell<FxHashMap<DictKey, Primitive>>>),
+    Struct(Rc<StructValue>),
+    Function(Rc<Closure>),
+}
+
+//values are moved around in registers as long as they fit in two words, a bigger payload (an
+//`Rc<str>` is two words on its own) sends every returned value through memory
+const _: () = assert!(std::mem::size_of::<Primitive>() == 16);
+
+//a float or a bool held in a whole word. When every payload is a word sized int or pointer a value
+//is copied as two words, a `f64` or `bool` payload made each copy a single wide move that stalls
+//when it reads a value stored just before as two words
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct FloatWord(u64);
+
+impl FloatWord {
+    #[inline(always)]
+    pub fn get(self) -> f64 {
+        f64::from_bits(self.0)
+    }
+}
+
+impl From<f64> for FloatWord {
+    #[inline(always)]
+    fn from(v: f64) -> Self {
+        FloatWord(v.to_bits())
+    }
+}
+
+impl Debug for FloatWord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.get(), f)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct BoolWord(u64);
+
+impl BoolWord {
+    #[inline(always)]
+    pub fn get(self) -> bool {
+        self.0 != 0
+    }
+}
+
+impl From<bool> for BoolWord {
+    #[inline(always)]
+    fn from(v: bool) -> Self {
+        BoolWord(v as u64)
+    }
+}
+
+impl Debug for BoolWord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.get(), f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DictKey {
+    Integer(i64),
+    BigInt(Rc<BigInt>),
+    String(Rc<String>),
+}
+
+impl fmt::Display for DictKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DictKey::Integer(v) => write!(f, "{}", v),
+            DictKey::BigInt(v) => write!(f, "{}", v),
+            DictKey::String(v) => write!(f, "{:?}", v),
+        }
+    }
+}
+
+impl From<DictKey> for Primitive {
+    fn from(key: DictKey) -> Self {
+        match key {
+            DictKey::Integer(v) => Primitive::Integer(v),
+            DictKey::BigInt(v) => Primitive::BigInt(v),
+            DictKey::String(v) => Primitive::String(v),
+        }
+    }
+}
+
+impl From<BigInt> for Primitive {
+    fn from(v: BigInt) -> Self {
+        match v.to_i64() {
+            Some(v) => Primitive::Integer(v),
+            None => Primitive::BigInt(Rc::new(v)),
+        }
+    }
+}
+
+macro_rules! impl_from_value {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for Primitive {
+                #[inline(always)]
+                fn from(v: $ty) -> Self {
+                    Primitive::$variant(v.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value!(
+    i64 => Integer,
+    f64 => Float,
+    bool => Boolean,
+    String => String,
+);
+
+impl From<&str> for Primitive {
+    fn from(v: &str) -> Self {
+        Primitive::String(Rc::new(v.to_string()))
+    }
+}
+
+impl From<Rc<str>> for Primitive {
+    fn from(v: Rc<str>) -> Self {
+        Primitive::from(&*v)
+    }
+}
+
+impl From<()> for Primitive {
+    fn from(_: ()) -> Self {
+        Primitive::VOID
+    }
+}
+
+thread_local! {
+    //the containers being printed, innermost last
+    static PRINTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+//prints a container with `body`, or with `repeat` when it is inside of itself
+fn fmt_container(
+    f: &mut fmt::Formatter,
+    ptr: usize,
+    repeat: fmt::Arguments,
+    body: impl FnOnce(&mut fmt::Formatter) -> fmt::Result,
+) -> fmt::Result {
+    if PRINTING.with_borrow(|v| v.contains(&ptr)) {
+        return f.write_fmt(repeat);
+    }
+    PRINTING.with_borrow_mut(|v| v.push(ptr));
+    let res = body(f);
+    PRINTING.with_borrow_mut(|v| v.pop());
+    res
 }
 
 impl fmt::Display for Primitive {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Primitive::Boolean(v) => write!(f, "{}", v),
+            Primitive::Boolean(v) => write!(f, "{}", v.get()),
             Primitive::Integer(v) => write!(f, "{}", v),
+            Primitive::BigInt(v) => write!(f, "{}", v),
+            //debug formatting keeps the fractional part so that the output reads back as a float
+            Primitive::Float(v) => write!(f, "{:?}", v.get()),
+            Primitive::String(v) => write!(f, "{}", v),
+            Primitive::Array(v) => {
+                fmt_container(f, Rc::as_ptr(v) as usize, format_args!("[...]"), |f| {
+                    write!(f, "[")?;
+                    for (i, u) in v.borrow().iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        u.fmt_nested(f)?;
+                    }
+                    write!(f, "]")
+                })
+            }
+            Primitive::Dict(v) => {
+                fmt_container(f, Rc::as_ptr(v) as usize, format_args!("{{...}}"), |f| {
+                    let v = v.borrow();
+                    //keys are sorted so that printing a dict is deterministic
+                    let mut entries = v.iter().collect::<Vec<_>>();
+                    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+                    write!(f, "{{")?;
+                    for (i, (key, u)) in entries.into_iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}: ", key)?;
+                        u.fmt_nested(f)?;
+                    }
+                    write!(f, "}}")
+                })
+            }
+            Primitive::Struct(v) => fmt_container(
+                f,
+                Rc::as_ptr(v) as usize,
+                format_args!("{} {{...}}", v.def().name()),
+                |f| v.fmt_fields(f),
+            ),
+            Primitive::Function(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+//every variable read clones its value, the derived clone was left out of line
+impl Clone for Primitive {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        match self {
+            Primitive::Integer(v) => Primitive::Integer(*v),
+            Primitive::BigInt(v) => Primitive::BigInt(Rc::clone(v)),
+            Primitive::Float(v) => Primitive::Float(*v),
+            Primitive::Boolean(v) => Primitive::Boolean(*v),
+            Primitive::String(v) => Primitive::String(Rc::clone(v)),
+            Primitive::Array(v) => Primitive::Array(Rc::clone(v)),
+            Primitive::Dict(v) => Primitive::Dict(Rc::clone(v)),
+            Primitive::Struct(v) => Primitive::Struct(Rc::clone(v)),
+            Primitive::Function(v) => Primitive::Function(Rc::clone(v)),
         }
     }
 }
 
 impl Primitive {
     pub const VOID: Primitive = Primitive::Integer(0);
+    //the drop glue of a value has a case for every `Rc` and is not inlined. The values most
+    //operations are done on hold none and skip it here
+    #[inline(always)]
+    pub fn release(self) {
+        if let Primitive::Integer(_) | Primitive::Float(_) | Primitive::Boolean(_) = self {
+            std::mem::forget(self);
+        }
+    }
+    //overwrites a variable or an element, releasing the value it held
+    #[inline(always)]
+    pub fn set(&mut self, value: Primitive) {
+        std::mem::replace(self, value).release();
+    }
+    pub fn is_int(&self) -> bool {
+        matches!(self, Primitive::Integer(_) | Primitive::BigInt(_))
+    }
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Primitive::Integer(_) | Primitive::BigInt(_) => "int",
+            Primitive::Float(_) => "float",
+            Primitive::Boolean(_) => "bool",
+            Primitive::String(_) => "string",
+            Primitive::Array(_) => "array",
+            Primitive::Dict(_) => "dict",
+            Primitive::Struct(_) => "struct",
+            Primitive::Function(_) => "function",
+        }
+    }
     pub fn as_int(&self) -> Result<i64> {
         match self {
-            Primitive::Boolean(v) => Ok(*v as i64),
+            Primitive::Boolean(v) => Ok(v.get() as i64),
             Primitive::Integer(v) => Ok(*v),
+            Primitive::BigInt(_) => Err(ArithmeticError::IntegerTooLarge.into()),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "int",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    pub fn as_float(&self) -> Result<f64> {
+        match self {
+            Primitive::Boolean(v) => Ok(v.get() as i64 as f64),
+            Primitive::Integer(v) => Ok(*v as f64),
+            //too large values become infinite
+            Primitive::BigInt(v) => Ok(v.to_f64().unwrap_or(f64::NAN)),
+            Primitive::Float(v) => Ok(v.get()),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "float",
+                found: self.type_name(),
+            }
+            .into()),
         }
     }
     pub fn as_bool(&self) -> Result<bool> {
         match self {
-            Primitive::Boolean(v) => Ok(*v),
+            Primitive::Boolean(v) => Ok(v.get()),
             Primitive::Integer(v) => Ok(*v > 0),
+            Primitive::BigInt(v) => Ok(v.is_positive()),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "bool",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    pub fn as_big_int(&self) -> Result<BigInt> {
+        match self {
+            Primitive::BigInt(v) => Ok(BigInt::clone(v)),
+            _ => Ok(BigInt::from(self.as_int()?)),
+        }
+    }
+    //conditions only accept bools in strict mode
+    #[inline]
+    pub fn as_condition(&self, strict: bool) -> Result<bool> {
+        match self {
+            Primitive::Boolean(v) => Ok(v.get()),
+            _ if strict => Err(TypeError::TypeMismatch {
+                expected: "bool",
+                found: self.type_name(),
+            }
+            .into()),
+            _ => self.as_bool(),
+        }
+    }
+    //a condition is not used once it is tested
+    #[inline(always)]
+    pub fn into_condition(self, strict: bool) -> Result<bool> {
+        let condition = self.as_condition(strict);
+        self.release();
+        condition
+    }
+    pub fn as_array(&self) -> Result<&Rc<RefCell<Vec<Primitive>>>> {
+        match self {
+            Primitive::Array(v) => Ok(v),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "array",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    //strings inside containers are quoted so that `["1"]` and `[1]` print differently
+    pub fn fmt_nested(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Primitive::String(v) => write!(f, "{:?}", v),
+            _ => write!(f, "{}", self),
+        }
+    }
+    //the value as error messages show it, quoted like inside of a container
+    pub fn quoted(&self) -> String {
+        match self {
+            Primitive::String(v) => format!("{:?}", v),
+            _ => self.to_string(),
+        }
+    }
+    fn checked_index(index: &Primitive, len: usize) -> Result<usize> {
+        //a big int is past the end of any array. Bools are not indices, like they are not keys of
+        //a dict
+        let value = match index {
+            Primitive::Integer(v) => usize::try_from(*v).ok(),
+            Primitive::BigInt(_) => None,
+            _ => {
+                return Err(TypeError::TypeMismatch {
+                    expected: "int",
+                    found: index.type_name(),
+                }
+                .into())
+            }
+        };
+        match value {
+            Some(v) if v < len => Ok(v),
+            _ => Err(IndexError::OutOfBounds {
+                index: index.to_string(),
+                len,
+            }
+            .into()),
+        }
+    }
+    pub fn as_dict(&self) -> Result<&Rc<RefCell<FxHashMap<DictKey, Primitive>>>> {
+        match self {
+            Primitive::Dict(v) => Ok(v),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "dict",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    pub fn as_struct(&self) -> Result<&Rc<StructValue>> {
+        match self {
+            Primitive::Struct(v) => Ok(v),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "struct",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    pub fn as_function(&self) -> Result<&Rc<Closure>> {
+        match self {
+            Primitive::Function(v) => Ok(v),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "function",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    pub fn as_key(&self) -> Result<DictKey> {
+        match self {
+            Primitive::Integer(v) => Ok(DictKey::Integer(*v)),
+            Primitive::BigInt(v) => Ok(DictKey::BigInt(Rc::clone(v))),
+            Primitive::String(v) => Ok(DictKey::String(Rc::clone(v))),
+            _ => Err(TypeError::TypeMismatch {
+                expected: "int or string",
+                found: self.type_name(),
+            }
+            .into()),
+        }
+    }
+    pub fn index(&self, index: &Primitive) -> Result<Primitive> {
+        if let Primitive::Dict(v) = self {
+            let key = index.as_key()?;
+            return v
+                .borrow()
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| IndexError::KeyNotFound(key.to_string()).into());
+        }
+        let array = self.as_array()?.borrow();
+        Ok(array[Self::checked_index(index, array.len())?].clone())
+    }
+    pub fn set_index(&self, index: &Primitive, value: Primitive) -> Result<()> {
+        if let Primitive::Dict(v) = self {
+            v.borrow_mut().insert(index.as_key()?, value);
+            return Ok(());
+        }
+        let mut array = self.as_array()?.borrow_mut();
+        let index = Self::checked_index(index, array.len())?;
+        array[index] = value;
+        Ok(())
+    }
+    #[inline(always)]
+    pub fn evaluate_binary(
+        &self,
+        other: &Primitive,
+        op: &OpType,
+        strict: bool,
+    ) -> Result<Primitive> {
+        if let (Primitive::Integer(u), Primitive::Integer(v)) = (self, other) {
+            if let Some(val) = Self::evaluate_ints(*u, *v, op) {
+                return Ok(val);
+            }
+        }
+        self.evaluate_checked(other, op, strict)
+    }
+    //most operations in a program are on two small integers, they are done here without going
+    //through the checks every other type needs. `None` leaves the rest, including every error, to
+    //`evaluate_checked`
+    #[inline(always)]
+    fn evaluate_ints(u: i64, v: i64, op: &OpType) -> Option<Primitive> {
+        Some(match op {
+            OpType::Add => Primitive::Integer(u.checked_add(v)?),
+            OpType::Sub => Primitive::Integer(u.checked_sub(v)?),
+            OpType::Mul => Primitive::Integer(u.checked_mul(v)?),
+            OpType::Div if v != 0 => Primitive::Integer(u.checked_div(v)?),
+            OpType::Mod if v != 0 => Primitive::Integer(u.checked_rem(v)?),
+            OpType::Gt => Primitive::from(u > v),
+            OpType::Lt => Primitive::from(u < v),
+            OpType::Gte => Primitive::from(u >= v),
+            OpType::Lte => Primitive::from(u <= v),
+            OpType::Eq => Primitive::from(u == v),
+            OpType::Neq => Primitive::from(u != v),
+            OpType::BitAnd => Primitive::Integer(u & v),
+            OpType::BitOr => Primitive::Integer(u | v),
+            OpType::BitXor => Primitive::Integer(u ^ v),
+            _ => return None,
+        })
+    }
+    #[inline(never)]
+    fn evaluate_checked(&self, other: &Primitive, op: &OpType, strict: bool) -> Result<Primitive> {
+        if strict {
+            self.check_strict(other, op)?;
+        }
+        self.evaluate_primary(other, op)
+    }
+    //in strict mode `&&` and `||` only take bools, `==` and `!=` only compare values of the same type
+    //and bools take no part in arithmetic, comparisons or bitwise operations.
+    //adding a bool to a string still formats it. Kept out of line so the default mode doesn't pay for it
+    #[cold]
+    fn check_strict(&self, other: &Primitive, op: &OpType) -> Result<()> {
+        match (self, other, op) {
+            (_, _, OpType::And | OpType::Or) => {
+                self.as_condition(true)?;
+                other.as_condition(true)?;
+                Ok(())
+            }
+            (_, _, OpType::Eq | OpType::Neq) if self.type_name() != other.type_name() => {
+                Err(self.unsupported(other, op))
+            }
+            (_, _, OpType::Eq | OpType::Neq)
+            | (Primitive::String(_), _, OpType::Add)
+            | (_, Primitive::String(_), OpType::Add) => Ok(()),
+            (Primitive::Boolean(_), _, _) | (_, Primitive::Boolean(_), _) => {
+                Err(self.unsupported(other, op))
+            }
+            _ => Ok(()),
         }
     }
     pub fn evaluate_primary(&self, other: &Primitive, op: &OpType) -> Result<Primitive> {
+        match (self, other) {
+            (Primitive::String(_), _) | (_, Primitive::String(_)) => {
+                return self.evaluate_string(other, op)
+            }
+            (
+                Primitive::Array(_)
+                | Primitive::Dict(_)
+                | Primitive::Struct(_)
+                | Primitive::Function(_),
+                _,
+            )
+            | (
+                _,
+                Primitive::Array(_)
+                | Primitive::Dict(_)
+                | Primitive::Struct(_)
+                | Primitive::Function(_),
+            ) => return self.evaluate_container(other, op),
+            (Primitive::Float(_), _) | (_, Primitive::Float(_)) => {
+                return self.evaluate_float(other, op)
+            }
+            (Primitive::BigInt(_), _) | (_, Primitive::BigInt(_)) => {
+                return self.evaluate_big_int(other, op)
+            }
+            _ => {}
+        }
         match op {
-            OpType::Add => Ok(Primitive::Integer(self.as_int()? + other.as_int()?)),
-            OpType::Sub => Ok(Primitive::Integer(self.as_int()? - other.as_int()?)),
-            OpType::Mul => Ok(Primitive::Integer(self.as_int()? * other.as_int()?)),
-            OpType::Div => Ok(Primitive::Integer(self.as_int()? / other.as_int()?)),
-            OpType::Mod => Ok(Primitive::Integer(self.as_int()? % other.as_int()?)),
-            OpType::And => Ok(Primitive::Boolean(self.as_bool()? && other.as_bool()?)),
-            OpType::Or => Ok(Primitive::Boolean(self.as_bool()? || other.as_bool()?)),
-            OpType::Gt => Ok(Primitive::Boolean(self.as_int()? > other.as_int()?)),
-            OpType::Lt => Ok(Primitive::Boolean(self.as_int()? < other.as_int()?)),
-            OpType::Gte => Ok(Primitive::Boolean(self.as_int()? >= other.as_int()?)),
-            OpType::Lte => Ok(Primitive::Boolean(self.as_int()? <= other.as_int()?)),
-            OpType::Eq => Ok(Primitive::Boolean(self.as_int()? == other.as_int()?)),
-            OpType::Neq => Ok(Primitive::Boolean(self.as_int()? != other.as_int()?)),
+            OpType::Add => self.or_big_int(other, self.as_int()?.checked_add(other.as_int()?), op),
+            OpType::Sub => self.or_big_int(other, self.as_int()?.checked_sub(other.as_int()?), op),
+            OpType::Mul => self.or_big_int(other, self.as_int()?.checked_mul(other.as_int()?), op),
+            OpType::Div | OpType::Mod if other.as_int()? == 0 => {
+                Err(ArithmeticError::DivisionByZero.into())
+            }
+            OpType::Div => self.or_big_int(other, self.as_int()?.checked_div(other.as_int()?), op),
+            OpType::Mod => self.or_big_int(other, self.as_int()?.checked_rem(other.as_int()?), op),
+            OpType::And => Ok(Primitive::from(self.as_bool()? && other.as_bool()?)),
+            OpType::Or => Ok(Primitive::from(self.as_bool()? || other.as_bool()?)),
+            OpType::Gt => Ok(Primitive::from(self.as_int()? > other.as_int()?)),
+            OpType::Lt => Ok(Primitive::from(self.as_int()? < other.as_int()?)),
+            OpType::Gte => Ok(Primitive::from(self.as_int()? >= other.as_int()?)),
+            OpType::Lte => Ok(Primitive::from(self.as_int()? <= other.as_int()?)),
+            OpType::Eq => Ok(Primitive::from(self.as_int()? == other.as_int()?)),
+            OpType::Neq => Ok(Primitive::from(self.as_int()? != other.as_int()?)),
             OpType::BitAnd => Ok(Primitive::Integer(self.as_int()? & other.as_int()?)),
             OpType::BitOr => Ok(Primitive::Integer(self.as_int()? | other.as_int()?)),
             OpType::BitXor => Ok(Primitive::Integer(self.as_int()? ^ other.as_int()?)),
-            OpType::ShiftLeft => Ok(Primitive::Integer(self.as_int()? << other.as_int()?)),
-            OpType::ShiftRight => Ok(Primitive::Integer(self.as_int()? >> other.as_int()?)),
+            OpType::ShiftLeft => {
+                let (u, v) = (self.as_int()?, Self::shift_amount(other)?);
+                //the shift overflowed if shifting back does not give the original value
+                let res = (v < i64::BITS as usize)
+                    .then(|| u << v)
+                    .filter(|res| res >> v == u);
+                self.or_big_int(other, res, op)
+            }
+            OpType::ShiftRight => Ok(Primitive::Integer(
+                self.as_int()? >> Self::shift_amount(other)?.min(i64::BITS as usize - 1),
+            )),
             _ => unreachable!(),
         }
     }
-    pub fn evaluate_unary(&self, op: &OpType) -> Result<Primitive> {
+    //`None` means the operation overflowed (this also covers `i64::MIN / -1`),
+    //it is then done again on big integers
+    fn or_big_int(&self, other: &Primitive, val: Option<i64>, op: &OpType) -> Result<Primitive> {
+        match val {
+            Some(v) => Ok(Primitive::Integer(v)),
+            None => self.evaluate_big_int(other, op),
+        }
+    }
+    //the slow path, only taken when one side is already a big integer or an `i64` operation overflowed
+    fn evaluate_big_int(&self, other: &Primitive, op: &OpType) -> Result<Primitive> {
+        let (u, v) = (self.as_big_int()?, other.as_big_int()?);
+        match op {
+            OpType::Add => Ok((u + v).into()),
+            OpType::Sub => Ok((u - v).into()),
+            OpType::Mul => Ok((u * v).into()),
+            OpType::Div | OpType::Mod if v.is_zero() => Err(ArithmeticError::DivisionByZero.into()),
+            OpType::Div => Ok((u / v).into()),
+            OpType::Mod => Ok((u % v).into()),
+            OpType::And => Ok(Primitive::from(u.is_positive() && v.is_positive())),
+            OpType::Or => Ok(Primitive::from(u.is_positive() || v.is_positive())),
+            OpType::Gt => Ok(Primitive::from(u > v)),
+            OpType::Lt => Ok(Primitive::from(u < v)),
+            OpType::Gte => Ok(Primitive::from(u >= v)),
+            OpType::Lte => Ok(Primitive::from(u <= v)),
+            OpType::Eq => Ok(Primitive::from(u == v)),
+            OpType::Neq => Ok(Primitive::from(u != v)),
+            OpType::BitAnd => Ok((u & v).into()),
+            OpType::BitOr => Ok((u | v).into()),
+            OpType::BitXor => Ok((u ^ v).into()),
+            OpType::ShiftLeft => {
+                let shift = Self::shift_amount(other)?;
+                if shift > Self::MAX_SHIFT {
+                    return Err(ArithmeticError::Overflow { op: op.symbol() }.into());
+                }
+                Ok((u << shift).into())
+            }
+            OpType::ShiftRight => Ok((u >> Self::shift_amount(other)?).into()),
+            _ => unreachable!(),
+        }
+    }
+    //left shifts past this many bits are rejected instead of allocating an enormous integer
+    const MAX_SHIFT: usize = 1 << 24;
+    fn shift_amount(val: &Primitive) -> Result<usize> {
+        match val {
+            Primitive::BigInt(v) if v.is_positive() => Ok(usize::MAX),
+            Primitive::BigInt(_) => Err(ArithmeticError::IntegerTooLarge.into()),
+            _ => {
+                let val = val.as_int()?;
+                if val < 0 {
+                    return Err(ArithmeticError::ShiftOutOfRange(val).into());
+                }
+                Ok(val as usize)
+            }
+        }
+    }
+    //as soon as one side is a float the other side is promoted, division and modulo
+    //then follow IEEE 754 (`%` keeps the sign of the dividend like integer modulo does)
+    fn evaluate_float(&self, other: &Primitive, op: &OpType) -> Result<Primitive> {
+        let (u, v) = (self.as_float()?, other.as_float()?);
+        match op {
+            OpType::Add => Ok(Primitive::from(u + v)),
+            OpType::Sub => Ok(Primitive::from(u - v)),
+            OpType::Mul => Ok(Primitive::from(u * v)),
+            OpType::Div => Ok(Primitive::from(u / v)),
+            OpType::Mod => Ok(Primitive::from(u % v)),
+            OpType::Gt => Ok(Primitive::from(u > v)),
+            OpType::Lt => Ok(Primitive::from(u < v)),
+            OpType::Gte => Ok(Primitive::from(u >= v)),
+            OpType::Lte => Ok(Primitive::from(u <= v)),
+            OpType::Eq => Ok(Primitive::from(u == v)),
+            OpType::Neq => Ok(Primitive::from(u != v)),
+            _ => Err(self.unsupported(other, op)),
+        }
+    }
+    //`+` builds a new array from both operands, equality compares the elements
+    //and structs are only equal to instances of the same declaration.
+    //functions have no structure to compare, they are only equal to themselves
+    fn evaluate_container(&self, other: &Primitive, op: &OpType) -> Result<Primitive> {
+        match (self, other, op) {
+            (Primitive::Array(u), Primitive::Array(v), OpType::Add) => {
+                let mut res = u.borrow().clone();
+                res.extend(v.borrow().iter().cloned());
+                Ok(Primitive::Array(Rc::new(RefCell::new(res))))
+            }
+            (_, _, OpType::Eq) => Ok(Primitive::from(self.equals(other, &mut Vec::new())?)),
+            (_, _, OpType::Neq) => Ok(Primitive::from(!self.equals(other, &mut Vec::new())?)),
+            _ => Err(self.unsupported(other, op)),
+        }
+    }
+    //`comparing` holds the pairs of containers compared further up. Containers holding themselves
+    //are equal when comparing them again can't find a difference, so a pair met a second time
+    //is taken as equal and the comparison ends
+    fn equals(&self, other: &Primitive, comparing: &mut Vec<(usize, usize)>) -> Result<bool> {
+        let pair = match (self, other) {
+            (Primitive::Array(u), Primitive::Array(v)) => {
+                (Rc::as_ptr(u) as usize, Rc::as_ptr(v) as usize)
+            }
+            (Primitive::Dict(u), Primitive::Dict(v)) => {
+                (Rc::as_ptr(u) as usize, Rc::as_ptr(v) as usize)
+            }
+            (Primitive::Struct(u), Primitive::Struct(v)) => {
+                (Rc::as_ptr(u) as usize, Rc::as_ptr(v) as usize)
+            }
+            _ => (0, 0),
+        };
+        if pair.0 != 0 {
+            if pair.0 == pair.1 || comparing.contains(&pair) {
+                return Ok(true);
+            }
+            comparing.push(pair);
+        }
+        let res = self.equals_values(other, comparing);
+        if pair.0 != 0 {
+            comparing.pop();
+        }
+        res
+    }
+    fn equals_values(
+        &self,
+        other: &Primitive,
+        comparing: &mut Vec<(usize, usize)>,
+    ) -> Result<bool> {
+        match (self, other) {
+            (Primitive::Array(u), Primitive::Array(v)) => {
+                let (u, v) = (u.borrow(), v.borrow());
+                if u.len() != v.len() {
+                    return Ok(false);
+                }
+                for (a, b) in u.iter().zip(v.iter()) {
+                    if !a.equals(b, comparing)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (Primitive::Dict(u), Primitive::Dict(v)) => {
+                let (u, v) = (u.borrow(), v.borrow());
+                if u.len() != v.len() {
+                    return Ok(false);
+                }
+                for (key, a) in u.iter() {
+                    match v.get(key) {
+                        Some(b) if a.equals(b, comparing)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+            (Primitive::Struct(u), Primitive::Struct(v)) => {
+                if !Rc::ptr_eq(u.def(), v.def()) {
+                    return Ok(false);
+                }
+                for (a, b) in u.fields().iter().zip(v.fields().iter()) {
+                    if !a.equals(b, comparing)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (Primitive::Function(u), Primitive::Function(v)) => Ok(Rc::ptr_eq(u, v)),
+            (
+                Primitive::Array(_)
+                | Primitive::Dict(_)
+                | Primitive::Struct(_)
+                | Primitive::Function(_),
+                _,
+            )
+            | (
+                _,
+                Primitive::Array(_)
+                | Primitive::Dict(_)
+                | Primitive::Struct(_)
+                | Primitive::Function(_),
+            ) => Ok(false),
+            _ => self.evaluate_primary(other, &OpType::Eq)?.as_bool(),
+        }
+    }
+    //`+` concatenates as soon as one side is a string, comparisons are lexicographic
+    //and equality between a string and any other type is always false
+    fn evaluate_string(&self, other: &Primitive, op: &OpType) -> Result<Primitive> {
+        match (self, other, op) {
+            (_, _, OpType::Add) => Ok(Primitive::String(format!("{}{}", self, other).into())),
+            (Primitive::String(u), Primitive::String(v), _) => match op {
+                OpType::Eq => Ok(Primitive::from(u == v)),
+                OpType::Neq => Ok(Primitive::from(u != v)),
+                OpType::Gt => Ok(Primitive::from(u > v)),
+                OpType::Lt => Ok(Primitive::from(u < v)),
+                OpType::Gte => Ok(Primitive::from(u >= v)),
+                OpType::Lte => Ok(Primitive::from(u <= v)),
+                _ => Err(self.unsupported(other, op)),
+            },
+            (_, _, OpType::Eq) => Ok(Primitive::from(false)),
+            (_, _, OpType::Neq) => Ok(Primitive::from(true)),
+            _ => Err(self.unsupported(other, op)),
+        }
+    }
+    fn unsupported(&self, other: &Primitive, op: &OpType) -> Box<PenguinError> {
+        TypeError::UnsupportedOperation {
+            op: op.symbol(),
+            lhs: self.type_name(),
+            rhs: other.type_name(),
+        }
+        .into()
+    }
+    pub fn evaluate_unary(&self, op: &OpType, strict: bool) -> Result<Primitive> {
+        match (self, op) {
+            (_, OpType::Opp) if strict => return Ok(Primitive::from(!self.as_condition(true)?)),
+            (Primitive::Boolean(_), _) if strict => {
+                return Err(TypeError::TypeMismatch {
+                    expected: "int",
+                    found: self.type_name(),
+                }
+                .into())
+            }
+            _ => {}
+        }
+        if let Primitive::Float(v) = self {
+            match op {
+                OpType::Add => return Ok(Primitive::Float(*v)),
+                OpType::Sub => return Ok(Primitive::from(-v.get())),
+                _ => {}
+            }
+        }
+        if let Primitive::BigInt(v) = self {
+            match op {
+                OpType::Add => return Ok(self.clone()),
+                OpType::Sub => return Ok((-BigInt::clone(v)).into()),
+                OpType::BitNot => return Ok((!BigInt::clone(v)).into()),
+                _ => {}
+            }
+        }
         match op {
             OpType::Add => Ok(Primitive::Integer(self.as_int()?)),
-            OpType::Sub => Ok(Primitive::Integer(-self.as_int()?)),
-            OpType::Opp => Ok(Primitive::Boolean(!self.as_bool()?)),
+            OpType::Sub => match self.as_int()?.checked_neg() {
+                Some(v) => Ok(Primitive::Integer(v)),
+                None => Ok((-self.as_big_int()?).into()),
+            },
+            OpType::Opp => Ok(Primitive::from(!self.as_bool()?)),
             OpType::BitNot => Ok(Primitive::Integer(!self.as_int()?)),
             _ => unreachable!(),
         }
@@ -1,78 +1,209 @@
-use crate::{parser::ast::ASTNode, ProgramState};
-use anyhow::Result;
+use std::{cell::Cell, iter::once};
 
-use super::primitive::Primitive;
+use crate::{
+    environment::environment::{Var, Variable},
+    error::{Location, PenguinError, Result},
+    parser::{ast::ASTNode, resolver::Resolver},
+    vm::{bytecode::Op, compiler::Compiler},
+    ProgramState,
+};
 
-#[derive(Debug)]
-pub struct Scope {
-    pub code: Box<[ASTNode]>,
-}
+use super::{expression::Expr, primitive::Primitive};
 
 #[derive(Debug)]
-pub enum ScopeError {
-    ReturnOutsideFunction,
-    ContinueOutsideLoop,
-    BreakOutsideLoop,
+pub struct Scope {
+    pub code: Box<[(ASTNode, Location)]>,
+    //whether a function literal appears somewhere inside the scope
+    capture: bool,
+    //size of the frame of a capturing scope, set by the resolver
+    slots: Cell<usize>,
+    //the expression ending a block used as a value
+    value: Option<Expr>,
 }
 
+//`break` and `continue` carry the label of the loop they target, an unlabeled one targets
+//the innermost loop. A loop passes the ones labeled for an outer loop on
 pub enum FlowStatement {
-    Continue,
-    Break,
+    Continue(Option<Var>),
+    Break(Option<Var>),
     Return(Primitive),
 }
 
-impl std::fmt::Display for ScopeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::ReturnOutsideFunction => {
-                write!(f, "a return statement may only be used within a function")
-            }
-            Self::ContinueOutsideLoop => {
-                write!(f, "a continue statement may only be used within a loop")
-            }
-            Self::BreakOutsideLoop => {
-                write!(f, "a break statement may only be used within a loop")
-            }
+impl Scope {
+    pub fn new(code: Box<[(ASTNode, Location)]>, capture: bool) -> Self {
+        Scope {
+            code,
+            capture,
+            slots: Cell::new(0),
+            value: None,
         }
     }
-}
-
-impl Scope {
-    pub fn new(code: Box<[ASTNode]>) -> Self {
-        Scope { code }
+    //a block used as a value, the parser leaves its value as the last statement
+    pub fn into_block(self) -> Self {
+        let mut code = self.code.into_vec();
+        let value = match code.pop() {
+            Some((ASTNode::Expr(v), _)) => v,
+            _ => unreachable!(),
+        };
+        Scope {
+            code: code.into_boxed_slice(),
+            value: Some(value),
+            ..self
+        }
+    }
+    //the variables declared directly in the scope
+    pub fn declarations(&self) -> impl Iterator<Item = Var> + '_ {
+        self.code.iter().filter_map(|(node, _)| match node {
+            ASTNode::Declaration(v) => Some(v.var()),
+            _ => None,
+        })
     }
     pub fn execute(
         &self,
         program: &ProgramState,
         is_function_scope: bool,
     ) -> Result<Option<FlowStatement>> {
-        if !is_function_scope {
-            program.environment.borrow_mut().open_scope();
-        }
-        let mut flow_statement: Option<FlowStatement> = None;
-        for node in self.code.iter() {
-            match node {
-                ASTNode::Expr(v) => v.execute(program).map(|_| ())?,
-                ASTNode::Declaration(v) => v.execute(program)?,
-                ASTNode::Assignment(v) => v.execute(program)?,
-                ASTNode::Scope(v) => flow_statement = v.execute(program, false)?,
-                ASTNode::IfElse(v) => flow_statement = v.execute(program)?,
-                ASTNode::WhileLoop(v) => flow_statement = v.execute(program)?,
-                ASTNode::Output(v) => v.execute(program)?,
-                ASTNode::BreakStatement => flow_statement = Some(FlowStatement::Break),
-                ASTNode::ContinueStatement => flow_statement = Some(FlowStatement::Continue),
-                ASTNode::ReturnStatement(v) => {
-                    let prim_value = v.execute(program)?;
-                    flow_statement = Some(FlowStatement::Return(prim_value));
-                }
-            }
+        if is_function_scope {
+            return self.execute_code(program);
+        }
+        if !self.capture {
+            return self.execute_code(program);
+        }
+        program
+            .environment
+            .borrow_mut()
+            .open_scope(self.slots.get());
+        //the scope is closed on errors too so that the environment stays usable after one
+        let flow_statement = self.execute_code(program);
+        program.environment.borrow_mut().close_scope();
+        flow_statement
+    }
+    //the parser makes sure no flow statement leaves a block used as a value.
+    //not inlined into `Expression::execute`, which slows down every other expression
+    #[inline(never)]
+    pub fn evaluate(&self, program: &ProgramState) -> Result<Primitive> {
+        let value = self.value.as_ref().unwrap();
+        if !self.capture {
+            self.execute_code(program)?;
+            return value.execute(program);
+        }
+        program
+            .environment
+            .borrow_mut()
+            .open_scope(self.slots.get());
+        let val = self
+            .execute_code(program)
+            .and_then(|_| value.execute(program));
+        program.environment.borrow_mut().close_scope();
+        val
+    }
+    fn execute_code(&self, program: &ProgramState) -> Result<Option<FlowStatement>> {
+        for (node, location) in self.code.iter() {
+            let flow_statement = node
+                .execute(program)
+                .map_err(|e| Self::locate(e, node, *location, program))?;
             if flow_statement.is_some() {
-                break;
+                return Ok(flow_statement);
             }
         }
+        Ok(None)
+    }
+    //the body of a function is resolved in the block the function opens for its arguments
+    pub fn resolve(&self, resolver: &mut Resolver, is_function_scope: bool) {
         if !is_function_scope {
-            program.environment.borrow_mut().close_scope();
+            resolver.open_block(self.declarations(), self.capture, false);
         }
-        Ok(flow_statement)
+        for (node, _) in self.code.iter() {
+            node.resolve(resolver);
+        }
+        if let Some(value) = &self.value {
+            value.resolve(resolver);
+        }
+        if !is_function_scope {
+            if let Some(slots) = resolver.close_block() {
+                self.slots.set(slots);
+            }
+        }
+    }
+    //the body of a `for` loop or of a `catch`, its block also holds the variable it starts with
+    //so that every iteration of a capturing body gets a variable of its own
+    pub fn resolve_binding(&self, resolver: &mut Resolver, var: &Variable) {
+        let declared = once(var.var()).chain(self.declarations());
+        resolver.open_block(declared, self.capture, false);
+        var.set_address(resolver.declare(var.var()).0);
+        for (node, _) in self.code.iter() {
+            node.resolve(resolver);
+        }
+        if let Some(slots) = resolver.close_block() {
+            self.slots.set(slots);
+        }
+    }
+    //runs the block with its variable set to `value`
+    pub fn execute_binding(
+        &self,
+        program: &ProgramState,
+        var: &Variable,
+        value: Primitive,
+    ) -> Result<Option<FlowStatement>> {
+        if !self.capture {
+            program
+                .environment
+                .borrow_mut()
+                .declare_var(var.address(), value)?;
+            return self.execute_code(program);
+        }
+        let mut environment = program.environment.borrow_mut();
+        environment.open_scope(self.slots.get());
+        let declared = environment.declare_var(var.address(), value);
+        drop(environment);
+        let flow_statement = declared.and_then(|_| self.execute_code(program));
+        program.environment.borrow_mut().close_scope();
+        flow_statement
+    }
+    //the value of the variable the block starts with is on the stack
+    pub fn compile_binding(&self, compiler: &mut Compiler, var: &Variable) {
+        if self.capture {
+            compiler.open_scope(self.slots.get());
+        }
+        //without a frame to open, the `ForNext` right before declares the variable itself
+        match compiler.last_op() {
+            Some(Op::ForNext(_, address @ None)) => *address = Some(var.address()),
+            _ => {
+                compiler.emit(Op::Declare(var.address()), None);
+            }
+        }
+        for (node, location) in self.code.iter() {
+            node.compile(compiler, *location);
+        }
+        if self.capture {
+            compiler.close_scope();
+        }
+    }
+    //a block used as a value leaves it on the stack
+    pub fn compile(&self, compiler: &mut Compiler, is_function_scope: bool) {
+        let capture = self.capture && !is_function_scope;
+        if capture {
+            compiler.open_scope(self.slots.get());
+        }
+        for (node, location) in self.code.iter() {
+            node.compile(compiler, *location);
+        }
+        if let Some(value) = &self.value {
+            value.compile(compiler);
+        }
+        if capture {
+            compiler.close_scope();
+        }
+    }
+    pub fn locate(
+        e: Box<PenguinError>,
+        node: &ASTNode,
+        location: Location,
+        program: &ProgramState,
+    ) -> Box<PenguinError> {
+        e.locate(location, || {
+            node.identifier()
+                .map(|v| program.environment.borrow().name(v).to_string())
+        })
     }
 }
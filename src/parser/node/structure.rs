@@ -0,0 +1,139 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::error::{NameError, Result, TypeError};
+use crate::{environment::environment::Var, ProgramState};
+
+use super::{expression::Expr, primitive::Primitive};
+
+#[derive(Debug)]
+pub struct StructDef {
+    name: Rc<str>,
+    fields: Box<[Rc<str>]>,
+}
+
+impl StructDef {
+    pub fn new(name: Rc<str>, fields: Box<[Rc<str>]>) -> Self {
+        Self { name, fields }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    fn position(&self, field: &str) -> Result<usize> {
+        self.fields
+            .iter()
+            .position(|v| v.as_ref() == field)
+            .ok_or_else(|| {
+                TypeError::UnknownField {
+                    name: self.name.to_string(),
+                    field: field.to_string(),
+                }
+                .into()
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct StructValue {
+    def: Rc<StructDef>,
+    fields: RefCell<Box<[Primitive]>>,
+}
+
+impl StructValue {
+    pub fn new(def: Rc<StructDef>, fields: Box<[Primitive]>) -> Self {
+        Self {
+            def,
+            fields: RefCell::new(fields),
+        }
+    }
+    pub fn def(&self) -> &Rc<StructDef> {
+        &self.def
+    }
+    pub fn fields(&self) -> std::cell::Ref<'_, Box<[Primitive]>> {
+        self.fields.borrow()
+    }
+    pub fn get_field(&self, field: &str) -> Result<Primitive> {
+        Ok(self.fields.borrow()[self.def.position(field)?].clone())
+    }
+    pub fn set_field(&self, field: &str, value: Primitive) -> Result<()> {
+        self.fields.borrow_mut()[self.def.position(field)?] = value;
+        Ok(())
+    }
+    pub fn fmt_fields(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {{ ", self.def.name)?;
+        for (i, (name, v)) in self
+            .def
+            .fields
+            .iter()
+            .zip(self.fields.borrow().iter())
+            .enumerate()
+        {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: ", name)?;
+            v.fmt_nested(f)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+#[derive(Debug)]
+pub struct StructInit {
+    var: Var,
+    name: Rc<str>,
+    fields: Box<[(Rc<str>, Expr)]>,
+}
+
+impl StructInit {
+    pub fn new(var: Var, name: Rc<str>, fields: Box<[(Rc<str>, Expr)]>) -> Self {
+        Self { var, name, fields }
+    }
+    pub fn fields(&self) -> impl Iterator<Item = &Expr> {
+        self.fields.iter().map(|(_, expr)| expr)
+    }
+    pub fn execute(&self, program: &ProgramState) -> Result<Primitive> {
+        let values = self
+            .fields()
+            .map(|expr| expr.execute(program))
+            .collect::<Result<Vec<Primitive>>>()?;
+        self.build(values, program)
+    }
+    //`values` are in the order the fields are written in, they are checked against the
+    //declaration and put in its order
+    pub fn build(&self, values: Vec<Primitive>, program: &ProgramState) -> Result<Primitive> {
+        let def = program
+            .environment
+            .borrow()
+            .get_struct(self.var)
+            .ok_or_else(|| NameError::UndeclaredStruct(self.name.to_string()))?;
+        let mut fields: Vec<Option<Primitive>> = vec![None; def.fields.len()];
+        for ((field, _), value) in self.fields.iter().zip(values) {
+            let pos = def.position(field)?;
+            if fields[pos].is_some() {
+                return Err(TypeError::DuplicateField {
+                    name: self.name.to_string(),
+                    field: field.to_string(),
+                }
+                .into());
+            }
+            fields[pos] = Some(value);
+        }
+        let fields = fields
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                v.ok_or_else(|| {
+                    TypeError::MissingField {
+                        name: self.name.to_string(),
+                        field: def.fields[i].to_string(),
+                    }
+                    .into()
+                })
+            })
+            .collect::<Result<Box<[Primitive]>>>()?;
+        Ok(Primitive::Struct(Rc::new(StructValue {
+            def,
+            fields: RefCell::new(fields),
+        })))
+    }
+}
@@ -1,25 +1,36 @@
-use anyhow::{anyhow, Result};
-use pest::error::{Error, ErrorVariant, InputLocation};
-use pest::{iterators::Pairs, pratt_parser::PrattParser, Parser};
-use pest::{Position, Span};
+use num_bigint::BigInt;
+use pest::error::{Error, InputLocation};
+use pest::{
+    iterators::{Pair, Pairs},
+    pratt_parser::PrattParser,
+    Parser,
+};
 use pest_derive::Parser;
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::{iter::from_fn, sync::OnceLock};
+use std::{
+    iter::{from_fn, Peekable},
+    sync::OnceLock,
+};
 
-use crate::environment::environment::{Environment, Var};
+use crate::environment::environment::{Environment, Var, Variable};
+use crate::error::{Location, ParseError, PenguinError, Result};
 use crate::ProgramState;
 
+use super::node::builtin::{Builtin, BuiltinCall};
 use super::node::expression::{Expr, ExpressionPool};
 use super::node::function::{ArgumentType, Func, FunctionCall};
+use super::node::structure::{StructDef, StructInit};
+use super::resolver::resolve;
 use super::{
     ast::ASTNode,
     node::{
-        conditional::IfElse,
-        declaration::{AssignOperation, Assignment, Declaration},
+        conditional::{IfElse, Match, MatchArm, Pattern},
+        declaration::{Accessor, AssignOperation, Assignment, Declaration},
+        exception::Try,
         expression::{ExprAtom, Expression, OpType},
         io::Output,
-        looping::WhileLoop,
+        looping::{ForLoop, WhileLoop},
         primitive::Primitive,
         scope::Scope,
     },
@@ -53,34 +64,157 @@ fn pratt_parser() -> &'static PrattParser<Rule> {
                 | Op::prefix(Rule::neg_op)
                 | Op::prefix(Rule::opp_op)
                 | Op::prefix(Rule::bit_not))
+            .op(Op::postfix(Rule::index) | Op::postfix(Rule::field) | Op::postfix(Rule::call))
     })
 }
 
 fn parse_expr<'a>(
     pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
 ) -> Result<usize> {
     pratt_parser()
-        .map_primary(|primary| match primary.as_rule() {
-            Rule::function_call => {
-                parse_function_call(primary.into_inner(), expr_pool, environment).map(|v| {
-                    expr_pool.borrow_mut().add(Expression::Literal {
-                        lhs: ExprAtom::FunctionCall(v),
+        .map_primary(|primary| {
+            let location = expr_pool.borrow().span_location(primary.as_span());
+            match primary.as_rule() {
+                Rule::function_call => {
+                    parse_function_call(primary.into_inner(), expr_pool, environment).map(|v| {
+                        expr_pool
+                            .borrow_mut()
+                            .add(Expression::Literal { lhs: v }, location)
                     })
+                }
+                Rule::function_declaration => {
+                    parse_function_declaration(primary.into_inner(), expr_pool, environment).map(
+                        |v| {
+                            expr_pool.borrow_mut().add(
+                                Expression::Literal {
+                                    lhs: ExprAtom::Function(Rc::new(v)),
+                                },
+                                location,
+                            )
+                        },
+                    )
+                }
+                Rule::array => Ok(Expression::Literal {
+                    lhs: ExprAtom::Array(
+                        primary
+                            .into_inner()
+                            .map(|v| parse_expr(v.into_inner(), expr_pool, environment).map(Expr))
+                            .collect::<Result<Box<[Expr]>>>()?,
+                    ),
+                })
+                .map(|v| expr_pool.borrow_mut().add(v, location)),
+                Rule::dict => Ok(Expression::Literal {
+                    lhs: ExprAtom::Dict(
+                        primary
+                            .into_inner()
+                            .map(|v| {
+                                let mut entry = v.into_inner();
+                                let key = parse_expr(
+                                    entry.next().unwrap().into_inner(),
+                                    expr_pool,
+                                    environment,
+                                )?;
+                                let val = parse_expr(
+                                    entry.next().unwrap().into_inner(),
+                                    expr_pool,
+                                    environment,
+                                )?;
+                                Ok((Expr(key), Expr(val)))
+                            })
+                            .collect::<Result<Box<[(Expr, Expr)]>>>()?,
+                    ),
                 })
+                .map(|v| expr_pool.borrow_mut().add(v, location)),
+                Rule::integer => Ok(expr_pool.borrow_mut().add(
+                    Expression::Literal {
+                        //literals that do not fit in an `i64` become big integers
+                        lhs: ExprAtom::Primitive(Primitive::from(
+                            primary.as_str().parse::<BigInt>().map_err(|_| {
+                                Box::<PenguinError>::from(ParseError::InvalidLiteral(
+                                    primary.as_str().to_string(),
+                                ))
+                                .locate(location, || None)
+                            })?,
+                        )),
+                    },
+                    location,
+                )),
+                Rule::struct_init => {
+                    parse_struct_init(primary.into_inner(), expr_pool, environment).map(|v| {
+                        expr_pool.borrow_mut().add(
+                            Expression::Literal {
+                                lhs: ExprAtom::Struct(Rc::new(v)),
+                            },
+                            location,
+                        )
+                    })
+                }
+                Rule::float => Ok(expr_pool.borrow_mut().add(
+                    Expression::Literal {
+                        lhs: ExprAtom::Primitive(Primitive::from(
+                            primary.as_str().parse::<f64>().map_err(|_| {
+                                Box::<PenguinError>::from(ParseError::InvalidLiteral(
+                                    primary.as_str().to_string(),
+                                ))
+                                .locate(location, || None)
+                            })?,
+                        )),
+                    },
+                    location,
+                )),
+                Rule::string => Ok(expr_pool.borrow_mut().add(
+                    Expression::Literal {
+                        lhs: ExprAtom::Primitive(Primitive::String(
+                            unescape(primary.into_inner().next().unwrap().as_str()).into(),
+                        )),
+                    },
+                    location,
+                )),
+                Rule::boolean => Ok(expr_pool.borrow_mut().add(
+                    Expression::Literal {
+                        lhs: ExprAtom::Primitive(Primitive::from(primary.as_str() == "true")),
+                    },
+                    location,
+                )),
+                Rule::identifier => Ok(expr_pool.borrow_mut().add(
+                    Expression::Literal {
+                        lhs: ExprAtom::Var(Variable::new(
+                            environment.borrow_mut().register(primary.as_str()),
+                        )),
+                    },
+                    location,
+                )),
+                Rule::if_expr => {
+                    if !primary
+                        .clone()
+                        .into_inner()
+                        .any(|v| v.as_rule() == Rule::else_value)
+                    {
+                        let e = Box::<PenguinError>::from(ParseError::MissingElse);
+                        return Err(e.locate(location, || None));
+                    }
+                    parse_if_else(primary.into_inner(), expr_pool, environment).map(|v| {
+                        expr_pool.borrow_mut().add(
+                            Expression::Literal {
+                                lhs: ExprAtom::IfElse(Box::new(v)),
+                            },
+                            location,
+                        )
+                    })
+                }
+                Rule::block => parse_body(primary, expr_pool, environment).map(|v| {
+                    expr_pool.borrow_mut().add(
+                        Expression::Literal {
+                            lhs: ExprAtom::Block(Box::new(v)),
+                        },
+                        location,
+                    )
+                }),
+                Rule::expr => parse_expr(primary.into_inner(), expr_pool, environment),
+                _ => unreachable!(),
             }
-            Rule::integer => Ok(expr_pool.borrow_mut().add(Expression::Literal {
-                lhs: ExprAtom::Primitive(Primitive::Integer(primary.as_str().parse::<i64>()?)),
-            })),
-            Rule::boolean => Ok(expr_pool.borrow_mut().add(Expression::Literal {
-                lhs: ExprAtom::Primitive(Primitive::Boolean(primary.as_str().parse::<bool>()?)),
-            })),
-            Rule::identifier => Ok(expr_pool.borrow_mut().add(Expression::Literal {
-                lhs: ExprAtom::Var(environment.borrow_mut().register(primary.as_str())),
-            })),
-            Rule::expr => parse_expr(primary.into_inner(), expr_pool, environment),
-            _ => unreachable!(),
         })
         .map_infix(|lhs, op, rhs| {
             let op_type = match op.as_rule() {
@@ -104,12 +238,20 @@ fn parse_expr<'a>(
                 Rule::bit_xor => OpType::BitXor,
                 _ => unreachable!(),
             };
-
-            Ok(expr_pool.borrow_mut().add(Expression::Binary {
-                lhs: Expr(lhs?),
-                op: op_type,
-                rhs: Expr(rhs?),
-            }))
+            let (lhs, rhs) = (lhs?, rhs?);
+            let mut expr_pool = expr_pool.borrow_mut();
+            let location = Location::new(
+                expr_pool.location(lhs).start(),
+                expr_pool.location(rhs).end(),
+            );
+            Ok(expr_pool.add(
+                Expression::Binary {
+                    lhs: Expr(lhs),
+                    op: op_type,
+                    rhs: Expr(rhs),
+                },
+                location,
+            ))
         })
         .map_prefix(|op, lhs| {
             let op_type = match op.as_rule() {
@@ -119,72 +261,202 @@ fn parse_expr<'a>(
                 Rule::bit_not => OpType::BitNot,
                 _ => unreachable!(),
             };
-            Ok(expr_pool.borrow_mut().add(Expression::Unary {
-                lhs: Expr(lhs?),
-                op: op_type,
-            }))
+            let lhs = lhs?;
+            let mut expr_pool = expr_pool.borrow_mut();
+            let location = Location::new(
+                expr_pool.span_location(op.as_span()).start(),
+                expr_pool.location(lhs).end(),
+            );
+            Ok(expr_pool.add(
+                Expression::Unary {
+                    lhs: Expr(lhs),
+                    op: op_type,
+                },
+                location,
+            ))
+        })
+        .map_postfix(|lhs, op| {
+            let lhs = lhs?;
+            let location = {
+                let expr_pool = expr_pool.borrow();
+                Location::new(
+                    expr_pool.location(lhs).start(),
+                    expr_pool.span_location(op.as_span()).end(),
+                )
+            };
+            let expr = match op.as_rule() {
+                Rule::index => Expression::Index {
+                    lhs: Expr(lhs),
+                    index: Expr(parse_expr(
+                        op.into_inner().next().unwrap().into_inner(),
+                        expr_pool,
+                        environment,
+                    )?),
+                },
+                Rule::field => Expression::Field {
+                    lhs: Expr(lhs),
+                    field: op.into_inner().next().unwrap().as_str().into(),
+                },
+                Rule::call => Expression::Call {
+                    lhs: Expr(lhs),
+                    argument_input: parse_call_argument(op.into_inner(), expr_pool, environment)?,
+                },
+                _ => unreachable!(),
+            };
+            Ok(expr_pool.borrow_mut().add(expr, location))
         })
         .parse(pairs)
 }
+fn unescape(raw: &str) -> String {
+    //the grammar only accepts known escape sequences so every backslash is followed by one of them
+    let mut res = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('r') => res.push('\r'),
+            Some('0') => res.push('\0'),
+            Some(v) => res.push(v),
+            None => unreachable!(),
+        }
+    }
+    res
+}
 fn parse_function_declaration<'a>(
     pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
 ) -> Result<Func> {
     let mut pairs = pairs.peekable();
-    let argument = from_fn(|| pairs.next_if(|pair| pair.as_rule().eq(&Rule::identifier)))
-        .map(|v| environment.borrow_mut().register(v.as_str()))
+    let argument_name = from_fn(|| pairs.next_if(|pair| pair.as_rule().eq(&Rule::identifier)))
+        .map(|v| check_name(v, expr_pool))
+        .collect::<Result<Vec<&str>>>()?;
+    if let Some(name) = argument_name
+        .iter()
+        .enumerate()
+        .find_map(|(i, name)| argument_name[..i].contains(name).then_some(name))
+    {
+        return Err(ParseError::DuplicateArgument(name.to_string()).into());
+    }
+    let argument = argument_name
+        .iter()
+        .map(|v| environment.borrow_mut().register(v))
         .collect::<Box<[Var]>>();
     let scope = parse_scope(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
-    Ok(Func::new(argument, scope))
+    Ok(Func::new(
+        argument,
+        argument_name.into_iter().map(Rc::from).collect(),
+        scope,
+    ))
+}
+//the name a declaration introduces, which can't be the name of a builtin
+fn check_name<'a>(pair: Pair<'a, Rule>, expr_pool: &RefCell<ExpressionPool>) -> Result<&'a str> {
+    let name = pair.as_str();
+    if Builtin::from_name(name).is_some() {
+        let location = expr_pool.borrow().span_location(pair.as_span());
+        let e = Box::<PenguinError>::from(ParseError::BuiltinName(name.to_string()));
+        return Err(e.locate(location, || None));
+    }
+    Ok(name)
 }
 fn parse_declaration<'a>(
     mut pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
 ) -> Result<Declaration> {
+    let identifier = check_name(pairs.next().unwrap(), expr_pool)?;
+    let v = parse_expr(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
+    Ok(Declaration::new(
+        environment.borrow_mut().register(identifier),
+        Expr(v),
+    ))
+}
+
+fn parse_struct_declaration<'a>(
+    mut pairs: Pairs<'a, Rule>,
+    environment: &RefCell<Environment>,
+) -> Result<()> {
     let identifier = pairs.next().unwrap().as_str();
-    let val = pairs.next().unwrap();
-    match val.as_rule() {
-        Rule::expr => {
-            let v = parse_expr(val.into_inner(), expr_pool, environment)?;
-            Ok(Declaration::Expression {
-                var: environment.borrow_mut().register(identifier),
-                expr: Expr(v),
-            })
-        }
-        Rule::function_declaration => {
-            let var = environment.borrow_mut().register(identifier);
-            Ok(Declaration::Function {
-                var,
-                func: Rc::new(parse_function_declaration(
-                    val.into_inner(),
-                    expr_pool,
-                    environment,
-                )?),
-            })
+    let mut fields: Vec<Rc<str>> = Vec::new();
+    for pair in pairs {
+        if fields.iter().any(|v| v.as_ref() == pair.as_str()) {
+            return Err(ParseError::DuplicateStructField {
+                name: identifier.to_string(),
+                field: pair.as_str().to_string(),
+            }
+            .into());
         }
-        _ => unreachable!(),
+        fields.push(pair.as_str().into());
     }
+    environment.borrow_mut().register_struct(
+        identifier,
+        StructDef::new(identifier.into(), fields.into_boxed_slice()),
+    )
 }
-
-fn parse_assignment<'a>(
+fn parse_struct_init<'a>(
     mut pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
+) -> Result<StructInit> {
+    let identifier = pairs.next().unwrap().as_str();
+    let fields = pairs
+        .map(|pair| {
+            let mut inner = pair.into_inner();
+            let field = inner.next().unwrap().as_str();
+            parse_expr(inner.next().unwrap().into_inner(), expr_pool, environment)
+                .map(|v| (Rc::from(field), Expr(v)))
+        })
+        .collect::<Result<Box<[(Rc<str>, Expr)]>>>()?;
+    Ok(StructInit::new(
+        environment.borrow_mut().register(identifier),
+        identifier.into(),
+        fields,
+    ))
+}
+fn parse_assignment<'a>(
+    pairs: Pairs<'a, Rule>,
+    expr_pool: &RefCell<ExpressionPool>,
+    environment: &RefCell<Environment>,
 ) -> Result<Assignment> {
+    let mut pairs = pairs.peekable();
     let identifier = pairs.next().unwrap().as_str();
+    let path =
+        from_fn(|| pairs.next_if(|pair| matches!(pair.as_rule(), Rule::index | Rule::field)))
+            .map(|v| match v.as_rule() {
+                Rule::index => parse_expr(
+                    v.into_inner().next().unwrap().into_inner(),
+                    expr_pool,
+                    environment,
+                )
+                .map(|v| Accessor::Index(Expr(v))),
+                Rule::field => Ok(Accessor::Field(
+                    v.into_inner().next().unwrap().as_str().into(),
+                )),
+                _ => unreachable!(),
+            })
+            .collect::<Result<Box<[Accessor]>>>()?;
     let op = match pairs.next().unwrap().as_rule() {
         Rule::assign_op => AssignOperation::AssignOp,
         Rule::cum_add => AssignOperation::AssignAdd,
         Rule::cum_sub => AssignOperation::AssignSub,
         Rule::cum_mul => AssignOperation::AssignMul,
         Rule::cum_div => AssignOperation::AssignDiv,
+        Rule::cum_shift_left => AssignOperation::AssignShiftLeft,
+        Rule::cum_shift_right => AssignOperation::AssignShiftRight,
+        Rule::cum_bit_and => AssignOperation::AssignBitAnd,
+        Rule::cum_bit_or => AssignOperation::AssignBitOr,
+        Rule::cum_bit_xor => AssignOperation::AssignBitXor,
         _ => unreachable!(),
     };
     let v = parse_expr(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
     Ok(Assignment::new(
         environment.borrow_mut().register(identifier),
+        path,
         op,
         Expr(v),
     ))
@@ -192,7 +464,7 @@ fn parse_assignment<'a>(
 fn parse_print_statement<'a>(
     mut pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
 ) -> Result<Output> {
     let output_type = pairs.next().unwrap().as_rule();
     match output_type {
@@ -206,132 +478,443 @@ fn parse_print_statement<'a>(
 fn parse_scope<'a>(
     pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
 ) -> Result<Scope> {
+    let capture = pairs
+        .clone()
+        .flatten()
+        .any(|pair| pair.as_rule() == Rule::function_declaration);
     Ok(Scope::new(
         pairs
-            .map(|pair| match pair.as_rule() {
-                Rule::expr => parse_expr(pair.into_inner(), expr_pool, environment)
-                    .map(|v| ASTNode::Expr(Expr(v))),
-                Rule::assignment => parse_assignment(pair.into_inner(), expr_pool, environment)
-                    .map(ASTNode::Assignment),
-                Rule::declaration => parse_declaration(pair.into_inner(), expr_pool, environment)
-                    .map(ASTNode::Declaration),
-                Rule::scope => {
-                    parse_scope(pair.into_inner(), expr_pool, environment).map(ASTNode::Scope)
-                }
-                Rule::ifelse => {
-                    parse_if_else(pair.into_inner(), expr_pool, environment).map(ASTNode::IfElse)
+            .filter_map(|pair| match pair.as_rule() {
+                //struct declarations only live in the environment, they leave nothing to execute
+                Rule::struct_declaration => {
+                    let location = expr_pool.borrow().span_location(pair.as_span());
+                    parse_struct_declaration(pair.into_inner(), environment)
+                        .err()
+                        .map(|e| Err(e.locate(location, || None)))
                 }
-                Rule::while_loop => parse_while_loop(pair.into_inner(), expr_pool, environment)
-                    .map(ASTNode::WhileLoop),
-                Rule::print_statement => {
-                    parse_print_statement(pair.into_inner(), expr_pool, environment)
-                        .map(ASTNode::Output)
-                }
-                Rule::continue_statement => Ok(ASTNode::ContinueStatement),
-                Rule::break_statement => Ok(ASTNode::BreakStatement),
-                Rule::return_statement => parse_expr(pair.into_inner(), expr_pool, environment)
-                    .map(|v| ASTNode::ReturnStatement(Expr(v))),
-                _ => unreachable!(),
+                _ => Some(Ok(pair)),
             })
-            .collect::<Result<Box<[ASTNode]>>>()?,
+            .map(|pair| {
+                pair.and_then(|pair| {
+                    let location = expr_pool.borrow().span_location(pair.as_span());
+                    match pair.as_rule() {
+                        Rule::expr => parse_expr(pair.into_inner(), expr_pool, environment)
+                            .map(|v| ASTNode::Expr(Expr(v))),
+                        Rule::assignment => {
+                            parse_assignment(pair.into_inner(), expr_pool, environment)
+                                .map(ASTNode::Assignment)
+                        }
+                        Rule::declaration => {
+                            parse_declaration(pair.into_inner(), expr_pool, environment)
+                                .map(ASTNode::Declaration)
+                        }
+                        Rule::scope => parse_scope(pair.into_inner(), expr_pool, environment)
+                            .map(ASTNode::Scope),
+                        Rule::ifelse => parse_if_else(pair.into_inner(), expr_pool, environment)
+                            .map(ASTNode::IfElse),
+                        Rule::match_statement => {
+                            parse_match(pair.into_inner(), expr_pool, environment)
+                                .map(ASTNode::Match)
+                        }
+                        Rule::while_loop => {
+                            parse_while_loop(pair.into_inner(), expr_pool, environment)
+                                .map(ASTNode::WhileLoop)
+                        }
+                        Rule::for_loop => parse_for_loop(pair.into_inner(), expr_pool, environment)
+                            .map(ASTNode::ForLoop),
+                        Rule::try_statement => {
+                            parse_try(pair.into_inner(), expr_pool, environment).map(ASTNode::Try)
+                        }
+                        Rule::print_statement => {
+                            parse_print_statement(pair.into_inner(), expr_pool, environment)
+                                .map(ASTNode::Output)
+                        }
+                        Rule::continue_statement => Ok(ASTNode::ContinueStatement(
+                            parse_label_target(pair.into_inner(), environment),
+                        )),
+                        Rule::break_statement => Ok(ASTNode::BreakStatement(parse_label_target(
+                            pair.into_inner(),
+                            environment,
+                        ))),
+                        Rule::return_statement => {
+                            parse_expr(pair.into_inner(), expr_pool, environment)
+                                .map(|v| ASTNode::ReturnStatement(Expr(v)))
+                        }
+                        Rule::throw_statement => {
+                            parse_expr(pair.into_inner(), expr_pool, environment)
+                                .map(|v| ASTNode::ThrowStatement(Expr(v)))
+                        }
+                        _ => unreachable!(),
+                    }
+                    .map(|v| (v, location))
+                    .map_err(|e| e.locate(location, || None))
+                })
+            })
+            .collect::<Result<Box<[(ASTNode, Location)]>>>()?,
+        capture,
     ))
 }
 
+//the body of an `if`, blocks used as values end with their value
+fn parse_body<'a>(
+    pair: Pair<'a, Rule>,
+    expr_pool: &RefCell<ExpressionPool>,
+    environment: &RefCell<Environment>,
+) -> Result<Scope> {
+    let block = pair.as_rule() == Rule::block;
+    let scope = parse_scope(pair.into_inner(), expr_pool, environment)?;
+    Ok(if block { scope.into_block() } else { scope })
+}
+
+//`if` statements and `if` expressions, the branches of the latter are blocks
 fn parse_if_else<'a>(
     pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
 ) -> Result<IfElse> {
     let mut pairs = pairs.peekable();
-    let if_clause = from_fn(|| pairs.next_if(|pair| pair.as_rule().ne(&Rule::r#else)))
+    let is_else = |pair: &Pair<'a, Rule>| matches!(pair.as_rule(), Rule::r#else | Rule::else_value);
+    let if_clause = from_fn(|| pairs.next_if(|pair| !is_else(pair)))
         .map(|pair| {
             let mut inner = pair.into_inner();
             let expr_parsed =
                 parse_expr(inner.next().unwrap().into_inner(), expr_pool, environment);
-            let scope_parsed =
-                parse_scope(inner.next().unwrap().into_inner(), expr_pool, environment);
+            let scope_parsed = parse_body(inner.next().unwrap(), expr_pool, environment);
             expr_parsed.and_then(|a| scope_parsed.map(|b| (Expr(a), b)))
         })
         .collect::<Result<Box<[(Expr, Scope)]>>>()?;
 
     let else_clause = pairs
         .next()
-        .map(|v| parse_scope(v.into_inner(), expr_pool, environment))
+        .map(|v| parse_body(v.into_inner().next().unwrap(), expr_pool, environment))
         .transpose()?;
     Ok(IfElse::new(if_clause, else_clause))
 }
 
-fn parse_while_loop<'a>(
+//the label a loop starts with
+fn parse_label<'a>(
+    pairs: &mut Peekable<Pairs<'a, Rule>>,
+    environment: &RefCell<Environment>,
+) -> Option<Var> {
+    let label = pairs.next_if(|pair| pair.as_rule() == Rule::label)?;
+    parse_label_target(label.into_inner(), environment)
+}
+
+//the label a `break` or `continue` targets
+fn parse_label_target<'a>(
+    mut pairs: Pairs<'a, Rule>,
+    environment: &RefCell<Environment>,
+) -> Option<Var> {
+    pairs
+        .next()
+        .map(|v| environment.borrow_mut().register(v.as_str()))
+}
+
+fn parse_match<'a>(
     mut pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
+    environment: &RefCell<Environment>,
+) -> Result<Match> {
+    let expr = parse_expr(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
+    let arms = pairs
+        .map(|arm| {
+            let mut inner = arm.into_inner();
+            let patterns = inner
+                .next()
+                .unwrap()
+                .into_inner()
+                .map(|v| parse_pattern(v, expr_pool))
+                .collect::<Result<Rc<[Pattern]>>>()?;
+            let mut next = inner.next().unwrap();
+            let mut guard = None;
+            if next.as_rule() == Rule::guard {
+                let expr = next.into_inner().next().unwrap();
+                guard = Some(Expr(parse_expr(expr.into_inner(), expr_pool, environment)?));
+                next = inner.next().unwrap();
+            }
+            let scope_parsed = parse_scope(next.into_inner(), expr_pool, environment)?;
+            Ok(MatchArm::new(patterns, guard, scope_parsed))
+        })
+        .collect::<Result<Box<[MatchArm]>>>()?;
+    Ok(Match::new(Expr(expr), arms))
+}
+
+fn parse_pattern(pair: Pair<'_, Rule>, expr_pool: &RefCell<ExpressionPool>) -> Result<Pattern> {
+    let int = |pair: Pair<'_, Rule>| {
+        pair.as_str()
+            .parse::<BigInt>()
+            .map(Primitive::from)
+            .map_err(|_| {
+                let location = expr_pool.borrow().span_location(pair.as_span());
+                Box::<PenguinError>::from(ParseError::InvalidLiteral(pair.as_str().to_string()))
+                    .locate(location, || None)
+            })
+    };
+    match pair.as_rule() {
+        Rule::match_int => int(pair).map(Pattern::Value),
+        Rule::boolean => Ok(Pattern::Value(Primitive::from(pair.as_str() == "true"))),
+        Rule::match_range => {
+            let mut inner = pair.into_inner();
+            let start = int(inner.next().unwrap())?;
+            let inclusive = inner.next().unwrap().as_rule() == Rule::inclusive;
+            let end = int(inner.next().unwrap())?;
+            Ok(Pattern::Range(start, end, inclusive))
+        }
+        Rule::wildcard => Ok(Pattern::Wildcard),
+        _ => unreachable!(),
+    }
+}
+
+fn parse_while_loop<'a>(
+    pairs: Pairs<'a, Rule>,
+    expr_pool: &RefCell<ExpressionPool>,
+    environment: &RefCell<Environment>,
 ) -> Result<WhileLoop> {
+    let mut pairs = pairs.peekable();
+    let label = parse_label(&mut pairs, environment);
     let v = parse_expr(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
     let scope_parsed = parse_scope(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
-    Ok(WhileLoop::new(Expr(v), scope_parsed))
+    Ok(WhileLoop::new(label, Expr(v), scope_parsed))
 }
 
-fn handle_parse_error(code: &str, e: Error<Rule>) -> anyhow::Error {
-    let err = match e.location {
-        InputLocation::Pos(v) => Error::new_from_pos(
-            ErrorVariant::<()>::CustomError {
-                message: String::from("Parse error occurred"),
-            },
-            Position::new(code, v).unwrap(),
-        ),
-        InputLocation::Span((u, v)) => Error::new_from_span(
-            ErrorVariant::<()>::CustomError {
-                message: String::from("Parse error occurred"),
-            },
-            Span::new(code, u, v).unwrap(),
-        ),
-    };
-    anyhow!(err)
+fn parse_for_loop<'a>(
+    pairs: Pairs<'a, Rule>,
+    expr_pool: &RefCell<ExpressionPool>,
+    environment: &RefCell<Environment>,
+) -> Result<ForLoop> {
+    let mut pairs = pairs.peekable();
+    let label = parse_label(&mut pairs, environment);
+    let var = check_name(pairs.next().unwrap(), expr_pool)?;
+    let var = environment.borrow_mut().register(var);
+    let start = parse_expr(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
+    let inclusive = pairs.next().unwrap().as_rule() == Rule::inclusive;
+    let end = parse_expr(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
+    let step = pairs
+        .next_if(|pair| pair.as_rule() == Rule::step)
+        .map(|pair| {
+            let expr = pair.into_inner().next().unwrap();
+            parse_expr(expr.into_inner(), expr_pool, environment).map(Expr)
+        })
+        .transpose()?;
+    let scope_parsed = parse_scope(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
+    Ok(ForLoop::new(
+        label,
+        var,
+        Expr(start),
+        Expr(end),
+        step,
+        inclusive,
+        scope_parsed,
+    ))
 }
 
-pub fn parse_function_call<'a>(
+fn parse_try<'a>(
     mut pairs: Pairs<'a, Rule>,
     expr_pool: &RefCell<ExpressionPool>,
-    environment: &RefCell<Environment<'a>>,
-) -> Result<FunctionCall> {
-    let identifier = pairs.next().unwrap().as_str();
-    let argument_input = pairs
-        .map(|v| match v.as_rule() {
+    environment: &RefCell<Environment>,
+) -> Result<Try> {
+    let body = parse_scope(pairs.next().unwrap().into_inner(), expr_pool, environment)?;
+    let mut catch = None;
+    let mut finally = None;
+    for pair in pairs {
+        let mut inner = pair.clone().into_inner();
+        match pair.as_rule() {
+            Rule::catch => {
+                let var = check_name(inner.next().unwrap(), expr_pool)?;
+                let var = environment.borrow_mut().register(var);
+                let scope =
+                    parse_scope(inner.next().unwrap().into_inner(), expr_pool, environment)?;
+                catch = Some((Variable::new(var), scope));
+            }
+            Rule::finally => {
+                let scope =
+                    parse_scope(inner.next().unwrap().into_inner(), expr_pool, environment)?;
+                finally = Some(scope);
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(Try::new(body, catch, finally))
+}
+
+//a labeled `break` or `continue` has to be inside of a loop with that label, in the same function
+fn check_labels<'a>(
+    pairs: Pairs<'a, Rule>,
+    labels: &mut Vec<&'a str>,
+    expr_pool: &ExpressionPool,
+) -> Result<()> {
+    for pair in pairs {
+        match pair.as_rule() {
             Rule::function_declaration => {
-                parse_function_declaration(v.into_inner(), expr_pool, environment)
-                    .map(ArgumentType::Func)
+                check_labels(pair.into_inner(), &mut Vec::new(), expr_pool)?
+            }
+            Rule::while_loop | Rule::for_loop => {
+                let label = pair.clone().into_inner().next();
+                let label = label.filter(|v| v.as_rule() == Rule::label);
+                let labeled = label.is_some();
+                if let Some(label) = label {
+                    labels.push(label.into_inner().next().unwrap().as_str());
+                }
+                check_labels(pair.into_inner(), labels, expr_pool)?;
+                if labeled {
+                    labels.pop();
+                }
             }
+            Rule::break_statement | Rule::continue_statement => {
+                let Some(label) = pair.into_inner().next() else {
+                    continue;
+                };
+                if !labels.contains(&label.as_str()) {
+                    let location = expr_pool.span_location(label.as_span());
+                    let e = ParseError::UnknownLabel(label.as_str().to_string());
+                    return Err(Box::<PenguinError>::from(e).locate(location, || None));
+                }
+            }
+            _ => check_labels(pair.into_inner(), labels, expr_pool)?,
+        }
+    }
+    Ok(())
+}
+
+//expressions cannot stop halfway, so no `return`, `break` or `continue` may leave a block used
+//as a value. `loops` holds the labels of the loops inside of the innermost such block
+fn check_values<'a>(
+    pairs: Pairs<'a, Rule>,
+    mut loops: Option<&mut Vec<Option<&'a str>>>,
+    expr_pool: &ExpressionPool,
+) -> Result<()> {
+    for pair in pairs {
+        let leaves = match (pair.as_rule(), loops.as_deref_mut()) {
+            (Rule::function_declaration, _) => {
+                check_values(pair.into_inner(), None, expr_pool)?;
+                continue;
+            }
+            (Rule::block, _) => {
+                check_values(pair.into_inner(), Some(&mut Vec::new()), expr_pool)?;
+                continue;
+            }
+            (Rule::while_loop | Rule::for_loop, Some(loops)) => {
+                let label = pair.clone().into_inner().next();
+                let label = label.filter(|v| v.as_rule() == Rule::label);
+                loops.push(label.map(|v| v.into_inner().next().unwrap().as_str()));
+                check_values(pair.into_inner(), Some(loops), expr_pool)?;
+                loops.pop();
+                continue;
+            }
+            (Rule::return_statement, Some(_)) => Some("return"),
+            (Rule::break_statement | Rule::continue_statement, Some(loops)) => {
+                let label = pair.clone().into_inner().next().map(|v| v.as_str());
+                let inside = match label {
+                    Some(label) => loops.contains(&Some(label)),
+                    None => !loops.is_empty(),
+                };
+                match pair.as_rule() {
+                    _ if inside => None,
+                    Rule::break_statement => Some("break"),
+                    _ => Some("continue"),
+                }
+            }
+            _ => None,
+        };
+        if let Some(keyword) = leaves {
+            let location = expr_pool.span_location(pair.as_span());
+            let e = Box::<PenguinError>::from(ParseError::LeavesValue(keyword));
+            return Err(e.locate(location, || None));
+        }
+        check_values(pair.into_inner(), loops.as_deref_mut(), expr_pool)?;
+    }
+    Ok(())
+}
+
+fn handle_parse_error(e: Error<Rule>, offset: usize) -> Box<PenguinError> {
+    let location = match e.location {
+        InputLocation::Pos(v) => Location::new(offset + v, offset + v),
+        InputLocation::Span((u, v)) => Location::new(offset + u, offset + v),
+    };
+    Box::<PenguinError>::from(ParseError::Syntax).locate(location, || None)
+}
+
+fn parse_call_argument<'a>(
+    pairs: Pairs<'a, Rule>,
+    expr_pool: &RefCell<ExpressionPool>,
+    environment: &RefCell<Environment>,
+) -> Result<Box<[ArgumentType]>> {
+    pairs
+        .map(|v| match v.as_rule() {
             Rule::expr => parse_expr(v.into_inner(), expr_pool, environment)
                 .map(|v| ArgumentType::Expr(Expr(v))),
-            Rule::ref_var => Ok(ArgumentType::Ref(
+            Rule::ref_var => Ok(ArgumentType::Ref(Variable::new(
                 environment
                     .borrow_mut()
                     .register(v.into_inner().next().unwrap().as_str()),
-            )),
+            ))),
             _ => unreachable!(),
         })
-        .collect::<Result<Box<[ArgumentType]>>>()?;
-    Ok(FunctionCall::new(
+        .collect::<Result<Box<[ArgumentType]>>>()
+}
+
+pub fn parse_function_call<'a>(
+    mut pairs: Pairs<'a, Rule>,
+    expr_pool: &RefCell<ExpressionPool>,
+    environment: &RefCell<Environment>,
+) -> Result<ExprAtom> {
+    let identifier = pairs.next().unwrap().as_str();
+    let pairs = pairs.next().unwrap().into_inner();
+    if let Some(builtin) = Builtin::from_name(identifier) {
+        let argument_input = pairs
+            .map(|v| match v.as_rule() {
+                Rule::expr => parse_expr(v.into_inner(), expr_pool, environment).map(Expr),
+                _ => Err(ParseError::BuiltinArgument(identifier.to_string()).into()),
+            })
+            .collect::<Result<Box<[Expr]>>>()?;
+        return Ok(ExprAtom::BuiltinCall(BuiltinCall::new(
+            builtin,
+            argument_input,
+        )));
+    }
+    let argument_input = parse_call_argument(pairs, expr_pool, environment)?;
+    Ok(ExprAtom::FunctionCall(FunctionCall::new(
         environment.borrow_mut().register(identifier),
         argument_input,
-    ))
+    )))
 }
 
-pub fn parse_ast(code: &str) -> Result<(ASTNode, ProgramState)> {
+pub fn parse_ast(code: &str) -> Result<(ASTNode, ProgramState<'_>)> {
     let pairs = CParser::parse(Rule::code, code)
-        .map_err(|e| handle_parse_error(code, e))?
+        .map_err(|e| handle_parse_error(e, 0).render(code))?
         .next()
         .unwrap()
         .into_inner();
     let expr_pool = RefCell::new(ExpressionPool::new());
     let environment = RefCell::new(Environment::default());
+    check_labels(pairs.clone(), &mut Vec::new(), &expr_pool.borrow())
+        .and_then(|_| check_values(pairs.clone(), None, &expr_pool.borrow()))
+        .map_err(|e| e.render(code))?;
 
-    parse_scope(pairs, &expr_pool, &environment).map(|v| {
-        (
-            ASTNode::Scope(v),
-            ProgramState::new(expr_pool.into_inner(), environment),
-        )
-    })
+    let scope = parse_scope(pairs, &expr_pool, &environment).map_err(|e| e.render(code))?;
+    let program = ProgramState::new(expr_pool.into_inner(), environment);
+    resolve(&scope.code, &program);
+    Ok((ASTNode::Scope(scope), program))
+}
+
+//parses code that continues `program`, it sees every name the program declared so far.
+//`offset` is where the code starts in the source of the whole program, errors are left unrendered
+//so they can be rendered against that source
+pub fn parse_entry(code: &str, offset: usize, program: &mut ProgramState) -> Result<Scope> {
+    let pairs = CParser::parse(Rule::entry, code)
+        .map_err(|e| handle_parse_error(e, offset))?
+        .next()
+        .unwrap()
+        .into_inner();
+    program.expr_pool.set_offset(offset);
+    check_labels(pairs.clone(), &mut Vec::new(), &program.expr_pool)?;
+    check_values(pairs.clone(), None, &program.expr_pool)?;
+    let expr_pool = RefCell::new(std::mem::take(&mut program.expr_pool));
+    let scope = parse_scope(pairs, &expr_pool, &program.environment);
+    program.expr_pool = expr_pool.into_inner();
+    let scope = scope?;
+    resolve(&scope.code, program);
+    Ok(scope)
 }
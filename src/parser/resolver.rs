@@ -0,0 +1,103 @@
+use fxhash::FxHashMap;
+
+use crate::{
+    environment::environment::{Address, Var},
+    error::Location,
+    ProgramState,
+};
+
+use super::{ast::ASTNode, node::expression::ExpressionPool};
+
+struct Block {
+    //names declared so far, the code of the block that follows a declaration sees it
+    declared: FxHashMap<Var, u32>,
+    //every name the block declares. A function written inside of the block sees all of them
+    //since it may be called once the rest of the block has run
+    hoisted: FxHashMap<Var, u32>,
+    //whether the block has a frame of its own, function bodies always do
+    frame: bool,
+    function: bool,
+}
+
+//gives every variable the address it is stored at, once the code is parsed. Names declared
+//at the top level outside of any block are globals, so they can be used by code parsed later
+pub struct Resolver<'p> {
+    expr_pool: &'p ExpressionPool,
+    blocks: Vec<Block>,
+    //slots handed out in each open frame, the first one is the top level frame
+    frames: Vec<u32>,
+}
+
+impl<'p> Resolver<'p> {
+    pub fn new(expr_pool: &'p ExpressionPool) -> Self {
+        Self {
+            expr_pool,
+            blocks: Vec::new(),
+            frames: vec![0],
+        }
+    }
+    pub fn expr_pool(&self) -> &'p ExpressionPool {
+        self.expr_pool
+    }
+    //`declared` are the names the block declares itself, they get their slots up front
+    pub fn open_block(&mut self, declared: impl Iterator<Item = Var>, frame: bool, function: bool) {
+        if frame {
+            self.frames.push(0);
+        }
+        let slots = self.frames.last_mut().unwrap();
+        let mut hoisted = FxHashMap::default();
+        for var in declared {
+            hoisted.entry(var).or_insert_with(|| {
+                *slots += 1;
+                *slots - 1
+            });
+        }
+        self.blocks.push(Block {
+            declared: FxHashMap::default(),
+            hoisted,
+            frame,
+            function,
+        });
+    }
+    //the number of slots of the frame of the block, if it has one
+    pub fn close_block(&mut self) -> Option<usize> {
+        let block = self.blocks.pop().unwrap();
+        block.frame.then(|| self.frames.pop().unwrap() as usize)
+    }
+    //the address of a variable declared in the current block and whether it is already declared there
+    pub fn declare(&mut self, var: Var) -> (Address, bool) {
+        let Some(block) = self.blocks.last_mut() else {
+            return (Address::Global(var), false);
+        };
+        let slot = block.hoisted[&var];
+        let redeclared = block.declared.insert(var, slot).is_some();
+        (Address::Local { depth: 0, slot }, redeclared)
+    }
+    pub fn lookup(&self, var: Var) -> Address {
+        let mut depth = 0;
+        let mut hoisted = false;
+        for block in self.blocks.iter().rev() {
+            let names = match hoisted {
+                true => &block.hoisted,
+                false => &block.declared,
+            };
+            if let Some(slot) = names.get(&var) {
+                return Address::Local { depth, slot: *slot };
+            }
+            depth += block.frame as u32;
+            hoisted |= block.function;
+        }
+        Address::Global(var)
+    }
+}
+
+pub fn resolve(code: &[(ASTNode, Location)], program: &ProgramState) {
+    let mut resolver = Resolver::new(&program.expr_pool);
+    for (node, _) in code.iter() {
+        node.resolve(&mut resolver);
+    }
+    program
+        .environment
+        .borrow_mut()
+        .reserve(resolver.frames[0] as usize);
+}
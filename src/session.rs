@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+
+use crate::{
+    environment::environment::Environment,
+    error::Location,
+    parser::{
+        ast::ASTNode,
+        node::{expression::ExpressionPool, primitive::Primitive, scope::Scope},
+        parser::parse_entry,
+    },
+    run_statement,
+    vm::{compiler::Compiler, vm::Vm},
+    Options, PenguinError, ProgramState,
+};
+
+//a program that is given one entry at a time, as in the REPL. The environment and the expressions
+//are kept between entries so an entry can use everything the previous ones declared
+pub struct Session {
+    program: ProgramState<'static>,
+    //every entry so far, errors are rendered against it so lines are counted from the first entry
+    source: String,
+}
+
+impl Session {
+    pub fn new(options: Options) -> Self {
+        let mut program =
+            ProgramState::new(ExpressionPool::new(), RefCell::new(Environment::default()));
+        program.options = options;
+        Session {
+            program,
+            source: String::new(),
+        }
+    }
+    //runs an entry and returns the value of its last statement if that statement is an expression.
+    //statements before an error keep their effects
+    pub fn run(&mut self, code: &str) -> Result<Option<Primitive>, PenguinError> {
+        if !self.source.is_empty() {
+            self.source.push('\n');
+        }
+        let offset = self.source.len();
+        self.source.push_str(code);
+        let scope =
+            parse_entry(code, offset, &mut self.program).map_err(|e| *e.render(&self.source))?;
+        if !self.program.options.no_fold {
+            self.program.expr_pool.fold(self.program.options.strict);
+        }
+        if self.program.options.vm {
+            return self.run_vm(&scope.code);
+        }
+        let mut value = None;
+        for (i, (node, location)) in scope.code.iter().enumerate() {
+            value = match node {
+                ASTNode::Expr(expr) if i + 1 == scope.code.len() => expr
+                    .execute(&self.program)
+                    .map(Some)
+                    .map_err(|e| Scope::locate(e, node, *location, &self.program)),
+                _ => run_statement(node, *location, &self.program).map(|_| None),
+            }
+            .map_err(|e| *e.render(&self.source))?;
+        }
+        Ok(value)
+    }
+    fn run_vm(&self, code: &[(ASTNode, Location)]) -> Result<Option<Primitive>, PenguinError> {
+        let chunk = Compiler::new(&self.program.expr_pool).compile_program(code, true);
+        let value = Vm::new(&self.program)
+            .run(&chunk)
+            .map_err(|e| *e.render(&self.source))?;
+        Ok(matches!(code.last(), Some((ASTNode::Expr(_), _))).then_some(value))
+    }
+}
+
+//whether the entry still has an open `{`, `(` or `[` and continues on the next line
+pub fn is_incomplete(code: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    depth > 0
+}
@@ -0,0 +1,135 @@
+use std::rc::Rc;
+
+use crate::{
+    environment::environment::{Address, Environment, Var},
+    error::{ControlFlowError, Location, PenguinError},
+    parser::node::{
+        builtin::Builtin,
+        conditional::Pattern,
+        expression::{Chain, OpType},
+        function::Func,
+        primitive::Primitive,
+        structure::StructInit,
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    //pushes a constant of the chunk
+    Constant(usize),
+    GetVar(Address),
+    //pop the value the variable is declared with or assigned, an assignment with an operator
+    //combines it with the current value first
+    Declare(Address),
+    Assign(Address, Option<OpType>),
+    //a declaration of a variable the block already declares, it fails
+    Redeclare,
+    Pop,
+    Unary(OpType),
+    Binary(OpType),
+    //a binary operation whose right hand side is a constant of the chunk
+    BinaryConstant(OpType, usize),
+    Chain(usize),
+    //`&&` and `||` jump over their right hand side when the left hand side decides the result,
+    //the left hand side is then the value of the expression
+    And(usize),
+    Or(usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    //jumps when none of the patterns of the chunk accepts the value on the stack, which stays there
+    JumpIfNoMatch(usize, usize),
+    //pops the value no arm of a match accepted and fails
+    NoMatch,
+    //checks that the value on the stack can be a bound or the step of a range
+    Bound,
+    //pops the bounds and, if there is one, the step of a range and starts looping over it.
+    //the flag is whether the range includes its end
+    Range(bool, bool),
+    //pushes the next value of the innermost range or jumps out of the loop when it is done.
+    //with an address the value is declared there instead of pushed
+    ForNext(usize, Option<Address>),
+    EndRange,
+    //only scopes capturing their variables have a frame, of that many slots
+    OpenScope(usize),
+    CloseScope,
+    //build a value out of that many values on the stack, for dicts it is the number of entries
+    Array(usize),
+    Dict(usize),
+    Struct(usize),
+    Closure(usize),
+    Index,
+    Field(usize),
+    //pop the value, the container and, for an index, the index that is written to
+    SetIndex(Option<OpType>),
+    SetField(usize, Option<OpType>),
+    //checks that the callee on the stack is a function of that many arguments,
+    //before the arguments are evaluated
+    CheckCall(usize),
+    Call(usize),
+    Builtin(Builtin, usize),
+    Print(usize),
+    //starts a `try`, whose errors jump to its `catch` and then to its `finally`
+    Try(Option<usize>, Option<usize>),
+    //the code the last `Try` protects is done
+    EndTry,
+    //runs the `finally` at the end of its `try`
+    Finally,
+    //leaves through the `finally` at that position, keeping the value on the stack if the flag
+    //is set, and comes back to the next op once it is done
+    Defer(usize, bool),
+    //goes on with what entered the `finally`: the code after it, a `Defer` or an error
+    EndFinally,
+    //a flow statement leaving a `finally` forgets what entered it
+    Discard,
+    Throw,
+    Return,
+    Fail(ControlFlowError),
+}
+
+//the name an error is reported on
+#[derive(Debug, Clone, Copy)]
+pub enum Name {
+    Var(Var),
+    Builtin(Builtin),
+}
+
+impl Name {
+    pub fn resolve(&self, environment: &Environment) -> String {
+        match self {
+            Name::Var(v) => environment.name(*v).to_string(),
+            Name::Builtin(v) => v.name().to_string(),
+        }
+    }
+}
+
+//the node an op was compiled from, errors raised by the op are located there
+#[derive(Debug, Clone, Copy)]
+pub struct Origin {
+    location: Location,
+    name: Option<Name>,
+}
+
+impl Origin {
+    pub fn new(location: Location, name: Option<Name>) -> Self {
+        Self { location, name }
+    }
+    pub fn locate(&self, e: Box<PenguinError>, environment: &Environment) -> Box<PenguinError> {
+        e.locate(self.location, || self.name.map(|v| v.resolve(environment)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    //`None` leaves the error to the caller, like a `break` outside of a loop inside of a function
+    //which is reported on the call
+    pub origin: Vec<Option<Origin>>,
+    pub constants: Vec<Primitive>,
+    pub names: Vec<Rc<str>>,
+    pub functions: Vec<Rc<Func>>,
+    pub structs: Vec<Rc<StructInit>>,
+    pub chains: Vec<Rc<Chain>>,
+    pub patterns: Vec<Rc<[Pattern]>>,
+    //for every call, the variables passed by reference in place of each argument
+    pub calls: Vec<Box<[Option<Address>]>>,
+}
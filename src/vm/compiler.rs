@@ -0,0 +1,296 @@
+use std::{ops::Range, rc::Rc};
+
+use crate::{
+    environment::environment::{Address, Var},
+    error::{ControlFlowError, Location},
+    parser::{
+        ast::ASTNode,
+        node::{
+            conditional::Pattern,
+            expression::{Chain, ExpressionPool},
+            function::Func,
+            primitive::Primitive,
+            structure::StructInit,
+        },
+    },
+};
+
+use super::bytecode::{Chunk, Op, Origin};
+
+struct Loop {
+    start: usize,
+    label: Option<Var>,
+    //whether it is a `for` loop, whose range is dropped when jumping out of it
+    range: bool,
+    //number of scopes open outside of the loop
+    scopes: usize,
+    breaks: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TryPart {
+    Body,
+    Catch,
+    Finally,
+}
+
+struct Try {
+    //the `Op::Try` starting it
+    at: usize,
+    //number of loops and scopes open outside of the `try`
+    loops: usize,
+    scopes: usize,
+    part: TryPart,
+    finally: bool,
+    //the flow statements leaving through the `finally`, they jump to its start
+    defers: Vec<usize>,
+}
+
+//compiles the code of one function, or of the top level, into a chunk
+pub struct Compiler<'p> {
+    expr_pool: &'p ExpressionPool,
+    chunk: Chunk,
+    //number of scopes with a frame opened since the start of the function, a flow statement
+    //closes the ones it jumps out of
+    scopes: usize,
+    loops: Vec<Loop>,
+    tries: Vec<Try>,
+    //the top level statement being compiled, a flow statement reaching the top level is reported on it.
+    //`None` inside of functions, where it is reported on the call
+    statement: Option<Location>,
+}
+
+impl<'p> Compiler<'p> {
+    pub fn new(expr_pool: &'p ExpressionPool) -> Self {
+        Self {
+            expr_pool,
+            chunk: Chunk::default(),
+            scopes: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+            statement: None,
+        }
+    }
+    //when `value` is set the chunk returns the value of the last statement if it is an expression,
+    //like the REPL shows it
+    pub fn compile_program(mut self, code: &[(ASTNode, Location)], value: bool) -> Chunk {
+        for (i, (node, location)) in code.iter().enumerate() {
+            self.statement = Some(*location);
+            match node {
+                //a return at the top level fails without evaluating its value
+                ASTNode::ReturnStatement(_) => self.fail(ControlFlowError::ReturnOutsideFunction),
+                ASTNode::Expr(expr) if value && i + 1 == code.len() => {
+                    expr.compile(&mut self);
+                    self.emit(Op::Return, None);
+                }
+                _ => node.compile(&mut self, *location),
+            }
+        }
+        self.finish()
+    }
+    pub fn finish(mut self) -> Chunk {
+        self.emit_constant(Primitive::VOID);
+        self.emit(Op::Return, None);
+        self.chunk
+    }
+    pub fn expr_pool(&self) -> &'p ExpressionPool {
+        self.expr_pool
+    }
+    //index of the next op, the target of a jump back to it
+    pub fn position(&self) -> usize {
+        self.chunk.code.len()
+    }
+    pub fn emit(&mut self, op: Op, origin: Option<Origin>) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.origin.push(origin);
+        self.chunk.code.len() - 1
+    }
+    pub fn add_constant(&mut self, value: Primitive) -> usize {
+        self.chunk.constants.push(value);
+        self.chunk.constants.len() - 1
+    }
+    pub fn emit_constant(&mut self, value: Primitive) {
+        let constant = self.add_constant(value);
+        self.emit(Op::Constant(constant), None);
+    }
+    pub fn last_op(&mut self) -> Option<&mut Op> {
+        self.chunk.code.last_mut()
+    }
+    //points the jump at `at` to the next op
+    pub fn patch(&mut self, at: usize) {
+        let target = self.position();
+        match &mut self.chunk.code[at] {
+            Op::Jump(v)
+            | Op::JumpIfFalse(v)
+            | Op::JumpIfNoMatch(_, v)
+            | Op::And(v)
+            | Op::Or(v)
+            | Op::ForNext(v, _)
+            | Op::Defer(v, _) => *v = target,
+            _ => unreachable!(),
+        }
+    }
+    pub fn add_name(&mut self, name: &str) -> usize {
+        match self.chunk.names.iter().position(|v| v.as_ref() == name) {
+            Some(i) => i,
+            None => {
+                self.chunk.names.push(name.into());
+                self.chunk.names.len() - 1
+            }
+        }
+    }
+    pub fn add_function(&mut self, func: Rc<Func>) -> usize {
+        self.chunk.functions.push(func);
+        self.chunk.functions.len() - 1
+    }
+    pub fn add_struct(&mut self, init: Rc<StructInit>) -> usize {
+        self.chunk.structs.push(init);
+        self.chunk.structs.len() - 1
+    }
+    pub fn add_chain(&mut self, chain: Rc<Chain>) -> usize {
+        self.chunk.chains.push(chain);
+        self.chunk.chains.len() - 1
+    }
+    pub fn add_patterns(&mut self, patterns: Rc<[Pattern]>) -> usize {
+        self.chunk.patterns.push(patterns);
+        self.chunk.patterns.len() - 1
+    }
+    pub fn add_call(&mut self, refs: Box<[Option<Address>]>) -> usize {
+        self.chunk.calls.push(refs);
+        self.chunk.calls.len() - 1
+    }
+    pub fn open_scope(&mut self, slots: usize) {
+        self.emit(Op::OpenScope(slots), None);
+        self.scopes += 1;
+    }
+    pub fn close_scope(&mut self) {
+        self.scopes -= 1;
+        self.emit(Op::CloseScope, None);
+    }
+    //emits the ops leaving the code down to `scopes` open scopes and `loops` open loops: scopes are
+    //closed, the ranges of `for` loops dropped and the `finally` blocks on the way run. The
+    //compiler keeps them open since the code after the jump is still inside of them.
+    //`value` is whether the value on the stack is kept across the `finally` blocks
+    fn leave(&mut self, scopes: usize, loops: usize, value: bool) {
+        let (mut open_scopes, mut open_loops) = (self.scopes, self.loops.len());
+        for i in (0..self.tries.len()).rev() {
+            let (at, part, finally) = (self.tries[i].at, self.tries[i].part, self.tries[i].finally);
+            let (try_scopes, try_loops) = (self.tries[i].scopes, self.tries[i].loops);
+            if try_loops < loops {
+                break;
+            }
+            self.close(try_scopes..open_scopes, try_loops..open_loops);
+            (open_scopes, open_loops) = (try_scopes, try_loops);
+            match (part, finally) {
+                (TryPart::Finally, _) => {
+                    self.emit(Op::Discard, None);
+                }
+                (TryPart::Catch, false) => {}
+                (TryPart::Body, false) => {
+                    self.emit(Op::EndTry, None);
+                }
+                (_, true) => {
+                    self.emit(Op::EndTry, None);
+                    let defer = self.emit(Op::Defer(at, value), None);
+                    self.tries[i].defers.push(defer);
+                }
+            }
+        }
+        self.close(scopes..open_scopes, loops..open_loops);
+    }
+    fn close(&mut self, scopes: Range<usize>, loops: Range<usize>) {
+        for _ in scopes {
+            self.emit(Op::CloseScope, None);
+        }
+        for i in loops.rev() {
+            if self.loops[i].range {
+                self.emit(Op::EndRange, None);
+            }
+        }
+    }
+    pub fn open_loop(&mut self, start: usize, label: Option<Var>, range: bool) {
+        self.loops.push(Loop {
+            start,
+            label,
+            range,
+            scopes: self.scopes,
+            breaks: Vec::new(),
+        });
+    }
+    //the loop a `break` or `continue` targets, the innermost one unless it has a label.
+    //the code inside of it is left before the jump
+    fn jump_out(&mut self, label: Option<Var>) -> Option<usize> {
+        let target = self
+            .loops
+            .iter()
+            .rposition(|v| label.is_none() || v.label == label)?;
+        self.leave(self.loops[target].scopes, target + 1, false);
+        Some(target)
+    }
+    pub fn close_loop(&mut self) {
+        for at in self.loops.pop().unwrap().breaks {
+            self.patch(at);
+        }
+    }
+    pub fn emit_break(&mut self, label: Option<Var>) {
+        let Some(target) = self.jump_out(label) else {
+            return self.fail(ControlFlowError::BreakOutsideLoop);
+        };
+        let at = self.emit(Op::Jump(0), None);
+        self.loops[target].breaks.push(at);
+    }
+    pub fn emit_continue(&mut self, label: Option<Var>) {
+        let Some(target) = self.jump_out(label) else {
+            return self.fail(ControlFlowError::ContinueOutsideLoop);
+        };
+        self.emit(Op::Jump(self.loops[target].start), None);
+    }
+    //the value to return is on the stack
+    pub fn emit_return(&mut self) {
+        if self.statement.is_some() {
+            self.emit(Op::Pop, None);
+            return self.fail(ControlFlowError::ReturnOutsideFunction);
+        }
+        self.leave(0, 0, true);
+        self.emit(Op::Return, None);
+    }
+    //errors in the body go to the `catch`, if there is one, then to the `finally`
+    pub fn open_try(&mut self, finally: bool) {
+        let at = self.emit(Op::Try(None, None), None);
+        self.tries.push(Try {
+            at,
+            loops: self.loops.len(),
+            scopes: self.scopes,
+            part: TryPart::Body,
+            finally,
+            defers: Vec::new(),
+        });
+    }
+    pub fn start_catch(&mut self) {
+        let target = self.position();
+        let current = self.tries.last_mut().unwrap();
+        current.part = TryPart::Catch;
+        if let Op::Try(catch, _) = &mut self.chunk.code[current.at] {
+            *catch = Some(target);
+        }
+    }
+    //the start of the `finally`, which the errors and flow statements leaving the `try` jump to
+    pub fn start_finally(&mut self) {
+        let target = self.position();
+        let current = self.tries.last_mut().unwrap();
+        current.part = TryPart::Finally;
+        if let Op::Try(_, finally) = &mut self.chunk.code[current.at] {
+            *finally = Some(target);
+        }
+        for at in std::mem::take(&mut current.defers) {
+            self.patch(at);
+        }
+    }
+    pub fn close_try(&mut self) {
+        self.tries.pop();
+    }
+    fn fail(&mut self, e: ControlFlowError) {
+        let origin = self.statement.map(|v| Origin::new(v, None));
+        self.emit(Op::Fail(e), origin);
+    }
+}
@@ -0,0 +1,373 @@
+use std::{cell::RefCell, rc::Rc};
+
+use fxhash::FxHashMap;
+
+use crate::{
+    environment::environment::Address,
+    error::{NameError, PenguinError, Result},
+    parser::node::{
+        conditional::no_match,
+        function::Closure,
+        looping::{range_bound, Range},
+        primitive::Primitive,
+    },
+    ProgramState,
+};
+
+use super::bytecode::{Chunk, Op};
+
+//a `try` running, what it catches the errors with and what is left once it does
+struct Handler {
+    catch: Option<usize>,
+    finally: Option<usize>,
+    stack: usize,
+    scopes: usize,
+    ranges: usize,
+}
+
+//what a `finally` goes on with once it is done
+enum Pending {
+    Next,
+    //a flow statement leaving through it, with the value it returns
+    Resume(usize, Option<Primitive>),
+    Error(Box<PenguinError>),
+}
+
+//runs chunks on a single value stack, calls run the chunk of the callee on top of the caller's values
+pub struct Vm<'p, 'a> {
+    program: &'p ProgramState<'a>,
+    stack: Vec<Primitive>,
+    //number of open scopes with a frame, the ones a failing chunk opened are closed on the way out
+    scopes: usize,
+    //ranges of the `for` loops running, innermost last
+    ranges: Vec<Range>,
+    handlers: Vec<Handler>,
+    pending: Vec<Pending>,
+}
+
+impl<'p, 'a> Vm<'p, 'a> {
+    pub fn new(program: &'p ProgramState<'a>) -> Self {
+        Self {
+            program,
+            stack: Vec::with_capacity(256),
+            scopes: 0,
+            ranges: Vec::new(),
+            handlers: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+    pub fn run(&mut self, chunk: &Chunk) -> Result<Primitive> {
+        let base = self.stack.len();
+        let scopes = self.scopes;
+        let ranges = self.ranges.len();
+        let (handlers, pending) = (self.handlers.len(), self.pending.len());
+        let mut pc = 0;
+        let res = loop {
+            let e = match self.execute(chunk, &mut pc) {
+                Ok(v) => break Ok(v),
+                Err(e) => match chunk.origin[pc - 1] {
+                    Some(origin) => origin.locate(e, &self.program.environment.borrow()),
+                    None => e,
+                },
+            };
+            if let Err(e) = self.handle(e, &mut pc, handlers) {
+                break Err(e);
+            }
+        };
+        //a return from inside of a `for` loop or a `try` leaves them behind
+        self.ranges.truncate(ranges);
+        self.handlers.truncate(handlers);
+        self.pending.truncate(pending);
+        if res.is_err() {
+            self.stack.truncate(base);
+            self.close_scopes(scopes);
+        }
+        res
+    }
+    fn close_scopes(&mut self, scopes: usize) {
+        while self.scopes > scopes {
+            self.scopes -= 1;
+            self.program.environment.borrow_mut().close_scope();
+        }
+    }
+    //gives the error to the innermost `try` of the chunk that takes it, above the `base`th one
+    fn handle(
+        &mut self,
+        e: Box<PenguinError>,
+        pc: &mut usize,
+        base: usize,
+    ) -> std::result::Result<(), Box<PenguinError>> {
+        while self.handlers.len() > base {
+            let handler = self.handlers.last_mut().unwrap();
+            let catch = handler.catch.take().filter(|_| e.is_catchable());
+            let finally = handler.finally;
+            let (stack, scopes, ranges) = (handler.stack, handler.scopes, handler.ranges);
+            //the handler of a `catch` with a `finally` stays until the `catch` is done
+            if catch.is_none() || finally.is_none() {
+                self.handlers.pop();
+            }
+            if catch.is_none() && finally.is_none() {
+                continue;
+            }
+            self.stack.truncate(stack);
+            self.close_scopes(scopes);
+            self.ranges.truncate(ranges);
+            match catch {
+                Some(target) => {
+                    self.push(self.program.catch(*e));
+                    *pc = target;
+                }
+                None => {
+                    self.pending.push(Pending::Error(e));
+                    *pc = finally.unwrap();
+                }
+            }
+            return Ok(());
+        }
+        Err(e)
+    }
+    //kept out of line, inlined in every op of `execute` it made the loop of the examples slower
+    #[inline(never)]
+    fn push(&mut self, val: Primitive) {
+        self.stack.push(val);
+    }
+    fn pop(&mut self) -> Primitive {
+        self.stack.pop().unwrap()
+    }
+    fn pop_n(&mut self, n: usize) -> Vec<Primitive> {
+        self.stack.split_off(self.stack.len() - n)
+    }
+    //`pc` is moved past an op before it runs, so on error the failing op is the one before it
+    fn execute(&mut self, chunk: &Chunk, pc: &mut usize) -> Result<Primitive> {
+        let program = self.program;
+        let strict = program.options.strict;
+        loop {
+            let op = chunk.code[*pc];
+            *pc += 1;
+            match op {
+                Op::Constant(i) => self.push(chunk.constants[i].clone()),
+                Op::GetVar(address) => {
+                    let val = program.environment.borrow().get_var(address)?;
+                    self.push(val);
+                }
+                Op::Declare(address) => {
+                    let val = self.pop();
+                    program.environment.borrow_mut().declare_var(address, val)?;
+                }
+                Op::Assign(address, op) => {
+                    let val = self.pop();
+                    let mut environment = program.environment.borrow_mut();
+                    match op {
+                        Some(op) => {
+                            let res = environment.update_var(address, &val, &op, strict);
+                            val.release();
+                            res?
+                        }
+                        None => environment.assign_var(address, val)?,
+                    }
+                }
+                Op::Redeclare => {
+                    self.pop();
+                    return Err(NameError::ReDeclaration.into());
+                }
+                Op::Pop => self.pop().release(),
+                Op::Unary(op) => {
+                    let val = self.pop().evaluate_unary(&op, strict)?;
+                    self.push(val);
+                }
+                //the result takes the place of the left hand side on the stack
+                Op::Binary(op) => {
+                    let rhs = self.pop();
+                    let lhs = self.stack.last_mut().unwrap();
+                    let val = lhs.evaluate_binary(&rhs, &op, strict);
+                    rhs.release();
+                    lhs.set(val?);
+                }
+                Op::BinaryConstant(op, i) => {
+                    let lhs = self.stack.last_mut().unwrap();
+                    let val = lhs.evaluate_binary(&chunk.constants[i], &op, strict)?;
+                    lhs.set(val);
+                }
+                Op::Chain(i) => {
+                    let val = chunk.chains[i].apply(self.pop(), strict)?;
+                    self.push(val);
+                }
+                Op::And(target) => {
+                    if !self.stack.last().unwrap().as_condition(strict)? {
+                        *pc = target;
+                    }
+                }
+                Op::Or(target) => {
+                    if self.stack.last().unwrap().as_condition(strict)? {
+                        *pc = target;
+                    }
+                }
+                Op::Jump(target) => *pc = target,
+                Op::JumpIfFalse(target) => {
+                    if !self.pop().into_condition(strict)? {
+                        *pc = target;
+                    }
+                }
+                Op::JumpIfNoMatch(i, target) => {
+                    let val = self.stack.last().unwrap();
+                    if !chunk.patterns[i].iter().any(|v| v.matches(val)) {
+                        *pc = target;
+                    }
+                }
+                Op::NoMatch => return Err(no_match(&self.pop()).into()),
+                Op::Bound => {
+                    let val = range_bound(self.stack.last().unwrap(), strict)?;
+                    *self.stack.last_mut().unwrap() = Primitive::Integer(val);
+                }
+                Op::Range(inclusive, step) => {
+                    let step = match step {
+                        true => self.pop().as_int()?,
+                        false => 1,
+                    };
+                    let end = self.pop().as_int()?;
+                    let start = self.pop().as_int()?;
+                    self.ranges.push(Range::new(start, end, step, inclusive)?);
+                }
+                Op::ForNext(target, address) => match self.ranges.last_mut().unwrap().next() {
+                    Some(v) => match address {
+                        Some(address) => program
+                            .environment
+                            .borrow_mut()
+                            .declare_var(address, Primitive::Integer(v))?,
+                        None => self.push(Primitive::Integer(v)),
+                    },
+                    None => *pc = target,
+                },
+                Op::EndRange => {
+                    self.ranges.pop();
+                }
+                Op::OpenScope(slots) => {
+                    self.scopes += 1;
+                    program.environment.borrow_mut().open_scope(slots)
+                }
+                Op::CloseScope => {
+                    self.scopes -= 1;
+                    program.environment.borrow_mut().close_scope()
+                }
+                Op::Array(n) => {
+                    let val = self.pop_n(n);
+                    self.stack
+                        .push(Primitive::Array(Rc::new(RefCell::new(val))));
+                }
+                Op::Dict(n) => {
+                    let mut dict = FxHashMap::default();
+                    let mut entries = self.pop_n(2 * n).into_iter();
+                    while let (Some(key), Some(val)) = (entries.next(), entries.next()) {
+                        dict.insert(key.as_key()?, val);
+                    }
+                    self.stack
+                        .push(Primitive::Dict(Rc::new(RefCell::new(dict))));
+                }
+                Op::Struct(i) => {
+                    let init = &chunk.structs[i];
+                    let values = self.pop_n(init.fields().count());
+                    self.push(init.build(values, program)?);
+                }
+                Op::Closure(i) => {
+                    let closure = Closure::new(
+                        Rc::clone(&chunk.functions[i]),
+                        program.environment.borrow().frame(),
+                    );
+                    self.push(Primitive::Function(Rc::new(closure)));
+                }
+                Op::Index => {
+                    let index = self.pop();
+                    let val = self.pop().index(&index)?;
+                    self.push(val);
+                }
+                Op::Field(i) => {
+                    let val = self.pop().as_struct()?.get_field(&chunk.names[i])?;
+                    self.push(val);
+                }
+                Op::SetIndex(op) => {
+                    let index = self.pop();
+                    let container = self.pop();
+                    let mut val = self.pop();
+                    if let Some(op) = op {
+                        val = container
+                            .index(&index)?
+                            .evaluate_binary(&val, &op, strict)?;
+                    }
+                    container.set_index(&index, val)?;
+                }
+                Op::SetField(i, op) => {
+                    let container = self.pop();
+                    let container = container.as_struct()?;
+                    let mut val = self.pop();
+                    if let Some(op) = op {
+                        val = container
+                            .get_field(&chunk.names[i])?
+                            .evaluate_binary(&val, &op, strict)?;
+                    }
+                    container.set_field(&chunk.names[i], val)?;
+                }
+                Op::CheckCall(n) => {
+                    let callee = self.stack.last().unwrap();
+                    callee.as_function()?.check_arity(n)?;
+                }
+                Op::Call(i) => {
+                    let refs = &chunk.calls[i];
+                    let args = self.pop_n(refs.len());
+                    let callee = self.pop();
+                    let val = self.call(callee.as_function()?, args, refs)?;
+                    self.push(val);
+                }
+                Op::Builtin(builtin, n) => {
+                    builtin.check_arity(n)?;
+                    let args = self.pop_n(n);
+                    self.push(builtin.call(&args)?);
+                }
+                Op::Print(end) => program.print(&self.pop(), &chunk.names[end])?,
+                Op::Try(catch, finally) => self.handlers.push(Handler {
+                    catch,
+                    finally,
+                    stack: self.stack.len(),
+                    scopes: self.scopes,
+                    ranges: self.ranges.len(),
+                }),
+                Op::EndTry => {
+                    self.handlers.pop();
+                }
+                Op::Finally => self.pending.push(Pending::Next),
+                Op::Defer(target, value) => {
+                    let value = value.then(|| self.pop());
+                    self.pending.push(Pending::Resume(*pc, value));
+                    *pc = target;
+                }
+                Op::EndFinally => match self.pending.pop().unwrap() {
+                    Pending::Next => {}
+                    Pending::Resume(target, value) => {
+                        self.stack.extend(value);
+                        *pc = target;
+                    }
+                    Pending::Error(e) => return Err(e),
+                },
+                Op::Discard => {
+                    self.pending.pop();
+                }
+                Op::Throw => return Err(program.throw(self.pop())),
+                Op::Return => return Ok(self.pop()),
+                Op::Fail(e) => return Err(e.into()),
+            }
+        }
+    }
+    fn call(
+        &mut self,
+        closure: &Closure,
+        args: Vec<Primitive>,
+        refs: &[Option<Address>],
+    ) -> Result<Primitive> {
+        let (func, env) = match closure {
+            Closure::Script { func, env } => (func, Rc::clone(env)),
+            Closure::Native(native) => return native.call(&args, self.program.options.strict),
+        };
+        func.call(env, args, refs.iter().copied(), self.program, || {
+            self.run(func.chunk())
+        })
+    }
+}
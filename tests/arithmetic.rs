@@ -0,0 +1,136 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok};
+    use penguin::{error::ArithmeticError, run_code, PenguinError};
+
+    #[test]
+    fn arithmetic_big_int_promotion() {
+        test_code_ok!(
+            "
+                gimme a = 9223372036854775807 + 1;
+                if a != 9223372036854775808 || a - 1 != 9223372036854775807 {
+                    undefined();
+                }
+                gimme b = -9223372036854775807 - 2;
+                if b + 2 != -9223372036854775807 || b >= -9223372036854775807 - 1 {
+                    undefined();
+                }
+                if -(-9223372036854775807 - 1) != 9223372036854775808 {
+                    undefined();
+                }
+                if (-9223372036854775807 - 1) / -1 != 9223372036854775808 {
+                    undefined();
+                }
+                if (-9223372036854775807 - 1) % -1 != 0 {
+                    undefined();
+                }
+                gimme c = 4611686018427387904;
+                c *= 4;
+                if c != 18446744073709551616 || c / 4 != 4611686018427387904 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme a = 99999999999999999999 * 99999999999999999999;
+                if a != 9999999999999999999800000000000000000001 {
+                    undefined();
+                }
+                if a % 1000 != 1 || -a % 1000 != -1 || -a / 10000000000000000000000000000000000000 != -999 {
+                    undefined();
+                }
+                if a <= 1 || a + 0.5 <= 1.0 || !(a > 0) || -a > 0 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn arithmetic_big_int_bitwise() {
+        test_code_ok!(
+            "
+                if 1 << 63 != 9223372036854775808 || 1 << 100 != 1267650600228229401496703205376 {
+                    undefined();
+                }
+                if (1 << 100) >> 99 != 2 || 1 >> 64 != 0 || -1 >> 200 != -1 || -8 >> 1 != -4 {
+                    undefined();
+                }
+                gimme a = 1 << 70;
+                if ~a != -a - 1 || ((a | 5) & a) != a || (a ^ 1) - a != 1 || ((a | 1) ^ a) != 1 {
+                    undefined();
+                }
+            "
+        );
+        test_code_failed!("gimme a = 1 << -1;");
+        test_code_failed!("gimme a = (1 << 70) << -1;");
+        test_code_failed!("gimme a = 1 << 100000000;");
+    }
+    #[test]
+    fn arithmetic_big_int_demotion() {
+        test_code_ok!(
+            "
+                gimme a = 9223372036854775807;
+                a += 1;
+                a -= 1;
+                gimme b = [1, 2, 3];
+                b[a - 9223372036854775805] = 4;
+                gimme d = {};
+                d[a + 1] = 1;
+                d[1 << 64] = 2;
+                if b[2] != 4 || d[9223372036854775808] != 1 || len(d) != 2 {
+                    undefined();
+                }
+            "
+        );
+        test_code_failed!(
+            "
+                gimme b = [1, 2, 3];
+                b[1 << 64] = 4;
+            "
+        );
+    }
+    #[test]
+    fn arithmetic_division_by_zero() {
+        test_code_failed!("gimme a = 1 / 0;");
+        test_code_failed!("gimme a = 1 % 0;");
+        test_code_failed!("gimme a = (1 << 64) / 0;");
+        test_code_failed!(
+            "
+                gimme a = 1;
+                gimme b = 0;
+                a /= b;
+            "
+        );
+        test_code_ok!(
+            "
+                gimme a = 1.0 / 0;
+                if a <= 1000000.0 || 7 % -2 != 1 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn arithmetic_error_location() {
+        let e = run_code("gimme a = 1;\ngimme b = a / (a - 1);").unwrap_err();
+        assert!(matches!(
+            e,
+            PenguinError::Arithmetic {
+                error: ArithmeticError::DivisionByZero,
+                ..
+            }
+        ));
+        assert_eq!(e.code(), "E0402");
+        assert_eq!(e.line_col(), Some((2, 11)));
+        let e = run_code("gimme a = 1;\n\na <<= -64;").unwrap_err();
+        assert_eq!(e.code(), "E0403");
+        assert_eq!(e.line_col(), Some((3, 1)));
+        let e = run_code("gimme a = 1 << 100000000;").unwrap_err();
+        assert_eq!(e.code(), "E0401");
+        assert_eq!(e.message(), "integer overflow in `<<`");
+        let e = run_code("gimme a = [1];\nprint(a[1 << 64]);").unwrap_err();
+        assert_eq!(e.line_col(), Some((2, 7)));
+    }
+}
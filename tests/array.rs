@@ -0,0 +1,140 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok, test_code_output};
+
+    #[test]
+    fn array_literal() {
+        test_code_ok!(
+            r#"
+                gimme a = [];
+                gimme b = [1, 2.5, "three", true, [4, 5],];
+                println(b);
+            "#
+        )
+    }
+    #[test]
+    fn array_index() {
+        test_code_ok!(
+            "
+                gimme a = [[1, 2], [3, 4]];
+                if a[0][1] != 2 || a[1][0] + a[1][1] != 7 || -a[0][0] != -1 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn array_index_assignment() {
+        test_code_ok!(
+            "
+                gimme a = [1, [2, 3]];
+                a[0] = 10;
+                a[0] -= 4;
+                a[1][1] *= 5;
+                if a != [6, [2, 15]] {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn array_shared_reference() {
+        test_code_ok!(
+            "
+                gimme a = [1];
+                gimme b = a;
+                b[0] = 2;
+                gimme set = (arr) => {
+                    push(arr, 3);
+                };
+                set(a);
+                if a != [2, 3] {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn array_builtin() {
+        test_code_ok!(
+            "
+                gimme a = [];
+                push(a, 1);
+                push(a, 2);
+                if len(a) != 2 || pop(a) != 2 || len(a) != 1 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn array_out_of_bounds_should_fail() {
+        test_code_failed!(
+            "
+                gimme a = [1, 2];
+                println(a[2]);
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = [1, 2];
+                a[-1] = 0;
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = [];
+                pop(a);
+            "
+        );
+    }
+    #[test]
+    fn array_invalid_operation_should_fail() {
+        test_code_failed!(
+            "
+                gimme a = 1;
+                a[0] = 1;
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = [1];
+                println(a[true && 1.5]);
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = [1, 2];
+                println(a[true]);
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = [1, 2];
+                a[false] = 3;
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = len([1], [2]);
+            "
+        );
+    }
+    #[test]
+    fn array_contains_itself() {
+        test_code_output!(
+            "
+                gimme a = [1];
+                push(a, a);
+                gimme b = [1];
+                push(b, b);
+                println(a);
+                println([a == b, a != [1, [1]]]);
+                push(b, 2);
+                println(a == b);
+            ",
+            "[1, [...]]\n[true, true]\nfalse\n"
+        );
+    }
+}
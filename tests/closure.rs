@@ -0,0 +1,219 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok};
+
+    #[test]
+    fn closure_outlives_function() {
+        test_code_ok!(
+            "
+                gimme make_counter = () => {
+                    gimme count = 0;
+                    return () => {
+                        count += 1;
+                        return count;
+                    };
+                };
+                gimme a = make_counter();
+                gimme b = make_counter();
+                a();
+                a();
+                if a() != 3 || b() != 1 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn closure_shares_frame() {
+        test_code_ok!(
+            "
+                gimme make_cell = () => {
+                    gimme value = 0;
+                    gimme get = () => {
+                        return value;
+                    };
+                    gimme set = (v) => {
+                        value = v;
+                    };
+                    return [get, set];
+                };
+                gimme cell = make_cell();
+                cell[1](42);
+                if cell[0]() != 42 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn closure_mutation_is_visible_outside() {
+        test_code_ok!(
+            "
+                gimme total = 0;
+                gimme add = (x) => {
+                    total += x;
+                };
+                add(2);
+                add(3);
+                if total != 5 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme outer = () => {
+                    gimme n = 1;
+                    gimme double = () => {
+                        n *= 2;
+                    };
+                    double();
+                    double();
+                    return n;
+                };
+                if outer() != 4 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme make = () => {
+                    gimme n = 0;
+                    gimme f = () => {
+                        return n;
+                    };
+                    n = 10;
+                    return f;
+                };
+                if make()() != 10 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn closure_captures_block_variables() {
+        test_code_ok!(
+            "
+                gimme fs = [];
+                gimme i = 0;
+                while i < 3 {
+                    gimme j = i;
+                    push(fs, () => {
+                        return j;
+                    });
+                    i += 1;
+                }
+                if fs[0]() != 0 || fs[1]() != 1 || fs[2]() != 2 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme fs = [];
+                gimme i = 0;
+                while i < 3 {
+                    push(fs, () => {
+                        return i;
+                    });
+                    i += 1;
+                }
+                if fs[0]() != 3 || fs[2]() != 3 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn closure_memoize() {
+        test_code_ok!(
+            "
+                gimme memo = (f) => {
+                    gimme cache = {};
+                    return (n) => {
+                        if !has(cache, n) {
+                            cache[n] = f(n);
+                        }
+                        return cache[n];
+                    };
+                };
+                gimme calls = 0;
+                gimme square = memo((n) => {
+                    calls += 1;
+                    return n * n;
+                });
+                square(4);
+                square(4);
+                if square(5) != 25 || calls != 2 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn closure_does_not_see_caller() {
+        test_code_failed!(
+            "
+                gimme make = () => {
+                    return () => {
+                        return secret;
+                    };
+                };
+                gimme f = make();
+                gimme call = () => {
+                    gimme secret = 1;
+                    return f();
+                };
+                call();
+            "
+        )
+    }
+    #[test]
+    fn closure_frame_is_dropped() {
+        use penguin::{parser::node::primitive::Primitive, Interpreter, Options};
+        use std::{cell::RefCell, rc::Rc};
+
+        for vm in [false, true] {
+            //keeps a weak reference to every closure it is given
+            let seen = Rc::new(RefCell::new(Vec::new()));
+            let watch = Rc::clone(&seen);
+            let interpreter = Interpreter::new()
+                .options(Options {
+                    vm,
+                    ..Default::default()
+                })
+                .function("watch", move |v: Primitive| {
+                    if let Primitive::Function(v) = v {
+                        watch.borrow_mut().push(Rc::downgrade(&v));
+                    }
+                });
+            interpreter
+                .run(
+                    "
+                        gimme f = (n) => {
+                            gimme g = () => { return n; };
+                            gimme arr = [1, 2, 3];
+                            watch(g);
+                            gimme h = 0;
+                            for i in 0..2 {
+                                h = () => { return i + g(); };
+                                watch(h);
+                            }
+                            return h();
+                        };
+                        gimme i = 0;
+                        while i < 3 {
+                            f(i);
+                            i += 1;
+                        }
+                    ",
+                )
+                .unwrap();
+            assert_eq!(seen.borrow().len(), 9);
+            assert!(seen.borrow().iter().all(|v| v.upgrade().is_none()));
+        }
+    }
+}
@@ -1,16 +1,67 @@
+//the options of each engine, for tests that build the programs themselves
+#[allow(dead_code)]
+pub fn engines() -> [penguin::Options; 2] {
+    [false, true].map(|vm| penguin::Options {
+        vm,
+        ..Default::default()
+    })
+}
+
+//every program is run both by walking the tree and on the vm, with and without folding
 #[macro_export]
 macro_rules! test_code_ok {
     ($test:expr) => {{
         use penguin::*;
-        let res = run_code($test);
-        assert!(res.is_ok());
+        for (vm, no_fold) in [(false, false), (false, true), (true, false), (true, true)] {
+            let res = run_code_with_options(
+                $test,
+                Options {
+                    vm,
+                    no_fold,
+                    ..Default::default()
+                },
+            );
+            assert!(res.is_ok(), "vm: {}, no_fold: {}, {:?}", vm, no_fold, res);
+        }
     }};
 }
 #[macro_export]
 macro_rules! test_code_failed {
     ($test:expr) => {{
         use penguin::*;
-        let res = run_code($test);
-        assert!(res.is_err());
+        for (vm, no_fold) in [(false, false), (false, true), (true, false), (true, true)] {
+            let res = run_code_with_options(
+                $test,
+                Options {
+                    vm,
+                    no_fold,
+                    ..Default::default()
+                },
+            );
+            assert!(res.is_err(), "vm: {}, no_fold: {}", vm, no_fold);
+        }
+    }};
+}
+#[macro_export]
+macro_rules! test_code_output {
+    ($test:expr, $output:expr) => {{
+        use penguin::*;
+        for (vm, no_fold) in [(false, false), (false, true), (true, false), (true, true)] {
+            let res = Interpreter::new()
+                .options(Options {
+                    vm,
+                    no_fold,
+                    ..Default::default()
+                })
+                .run_to_string($test);
+            assert_eq!(
+                res.as_deref().ok(),
+                Some($output),
+                "vm: {}, no_fold: {}, {:?}",
+                vm,
+                no_fold,
+                res
+            );
+        }
     }};
 }
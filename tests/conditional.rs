@@ -1,7 +1,7 @@
 mod common;
 #[cfg(test)]
 mod tests {
-    use crate::test_code_ok;
+    use crate::{test_code_failed, test_code_ok, test_code_output};
 
     #[test]
     fn empty_nested_if() {
@@ -27,4 +27,102 @@ mod tests {
             "
         )
     }
+    #[test]
+    fn match_patterns() {
+        test_code_output!(
+            "
+                gimme f = (x) => {
+                    match x {
+                        0 => { return \"zero\"; }
+                        1 | 2 => { return \"small\"; }
+                        -5..0 => { return \"negative\"; }
+                        3..=9 if x % 2 == 1 => { return \"odd\"; }
+                        3..=9 => { return \"even\"; }
+                        true => { return \"yes\"; }
+                        _ => { return \"other\"; }
+                    }
+                };
+                for i in -1..11 {
+                    print(f(i) + \" \");
+                }
+                print(f(true));
+                print(f(false));
+            ",
+            "negative zero small small odd even odd even odd even odd other yesother"
+        );
+    }
+    #[test]
+    fn match_types() {
+        //bools and ints never match each other, big ints compare by value
+        test_code_output!(
+            "
+                match 1 {
+                    true => { print(\"bool\"); },
+                    1 => { print(\"int\"); },
+                }
+                match 9223372036854775807 + 1 {
+                    9223372036854775808 => { print(\"big\"); }
+                    _ => {}
+                }
+                match 3 {
+                    0..9223372036854775808 => { print(\"range\"); }
+                }
+            ",
+            "intbigrange"
+        );
+    }
+    #[test]
+    fn match_flow() {
+        test_code_output!(
+            "
+                gimme total = 0;
+                for i in 0..10 {
+                    match i {
+                        2 => { continue; }
+                        5 => { break; }
+                        _ => {
+                            gimme j = i;
+                            total += (() => { return j; })();
+                        }
+                    }
+                }
+                println(total);
+            ",
+            "8\n"
+        );
+    }
+    #[test]
+    fn match_no_arm() {
+        test_code_failed!("match 3 {\n    0..3 => {}\n}");
+        test_code_failed!("match 1.0 {\n    1 => {}\n}");
+        test_code_failed!("match \"a\" {\n    _ if 1 / 0 => {}\n}");
+    }
+    #[test]
+    fn if_expression() {
+        test_code_output!(
+            "
+                gimme sign = (x) => {
+                    return if x < 0 { -1 } elif x == 0 { 0 } else { 1 };
+                };
+                print(sign(-5) + \" \" + sign(0) + \" \" + sign(7) + \" \");
+                gimme a = 1 + if false { 1 } else { 2 } * 10;
+                print(a + \" \");
+                gimme fact = (n) => {
+                    return if n <= 1 { 1 } else { n * fact(n - 1) };
+                };
+                println(fact(10));
+                if a > 0 { 5 } else { 6 };
+            ",
+            "-1 0 1 21 3628800\n"
+        );
+    }
+    #[test]
+    fn if_expression_errors() {
+        test_code_failed!("gimme a = if true { 1 };");
+        test_code_failed!("gimme a = if 1.5 { 1 } else { 2 };");
+        test_code_failed!("gimme a = if true { undefined } else { 2 };");
+        test_code_failed!(
+            "gimme f = () => {\n    gimme a = if true { return 1; 2 } else { 3 };\n};"
+        );
+    }
 }
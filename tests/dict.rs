@@ -0,0 +1,106 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok, test_code_output};
+
+    #[test]
+    fn dict_literal() {
+        test_code_ok!(
+            r#"
+                gimme a = {};
+                gimme b = {"one": 1, 2: "two", "nested": {"x": [1, 2]},};
+                println(b);
+                {}
+            "#
+        )
+    }
+    #[test]
+    fn dict_lookup_and_assignment() {
+        test_code_ok!(
+            r#"
+                gimme a = {"x": 1, 2: [3]};
+                a["y"] = 2;
+                a["x"] += 10;
+                a[2][0] = 4;
+                if a["x"] != 11 || a["y"] != 2 || a != {2: [4], "x": 11, "y": 2} {
+                    undefined();
+                }
+            "#
+        )
+    }
+    #[test]
+    fn dict_builtin() {
+        test_code_ok!(
+            r#"
+                gimme a = {"b": 2, "a": 1, 0: 0};
+                if !has(a, "a") || has(a, "c") || len(a) != 3 {
+                    undefined();
+                }
+                if keys(a) != [0, "a", "b"] {
+                    undefined();
+                }
+                if remove(a, "a") != 1 || has(a, "a") || len(a) != 2 {
+                    undefined();
+                }
+            "#
+        )
+    }
+    #[test]
+    fn dict_iteration() {
+        test_code_ok!(
+            r#"
+                gimme a = {"a": 1, "b": 2, "c": 3};
+                gimme k = keys(a);
+                gimme i = 0;
+                gimme total = 0;
+                while i < len(k) {
+                    total += a[k[i]];
+                    i += 1;
+                }
+                if total != 6 {
+                    undefined();
+                }
+            "#
+        )
+    }
+    #[test]
+    fn dict_invalid_key_should_fail() {
+        test_code_failed!(
+            r#"
+                gimme a = {"a": 1};
+                println(a["b"]);
+            "#
+        );
+        test_code_failed!(
+            r#"
+                gimme a = {1.5: 1};
+            "#
+        );
+        test_code_failed!(
+            r#"
+                gimme a = {};
+                a[[1]] = 1;
+            "#
+        );
+        test_code_failed!(
+            r#"
+                gimme a = {};
+                remove(a, "a");
+            "#
+        );
+    }
+    #[test]
+    fn dict_contains_itself() {
+        test_code_output!(
+            r#"
+                gimme a = {"n": 1};
+                a["self"] = a;
+                gimme b = {"n": 1};
+                b["self"] = b;
+                println(a);
+                println([a == b, [a] == [b]]);
+            "#,
+            "{\"n\": 1, \"self\": {...}}\n[true, true]\n"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use penguin::{error::NameError, run_code, run_code_with_options, PenguinError};
+
+    fn error_message(code: &str) -> String {
+        run_code(code).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn error_undeclared_variable() {
+        let message = error_message("gimme a = 1;\nprintln(a + b);");
+        assert!(message.contains("2:13"));
+        assert!(message.contains("println(a + b);"));
+        assert!(message.contains("variable was not declared: `b`"));
+    }
+    #[test]
+    fn error_function_arity() {
+        let message = error_message(
+            "
+gimme f = (x) => {
+    return x;
+};
+f(1, 2);",
+        );
+        assert!(message.contains("5:1"));
+        assert!(message.contains("too many arguments in function call: `f`"));
+    }
+    #[test]
+    fn error_inside_function() {
+        let message = error_message(
+            "
+gimme f = () => {
+    gimme x = [1];
+    return x[3];
+};
+f();",
+        );
+        assert!(message.contains("4:12"));
+        assert!(message.contains("index 3 is out of bounds"));
+    }
+    #[test]
+    fn error_big_int_index() {
+        for options in crate::common::engines() {
+            let e = run_code_with_options(
+                "gimme a = [1];\nprintln(a[-99999999999999999999]);",
+                options,
+            )
+            .unwrap_err();
+            assert_eq!(
+                e.message(),
+                "index -99999999999999999999 is out of bounds for array of length 1"
+            );
+        }
+    }
+    #[test]
+    fn error_statement() {
+        let message = error_message("gimme a = 1;\ngimme a = 2;");
+        assert!(message.contains("2:1"));
+        assert!(message.contains("variable has been previously declared: `a`"));
+        let message = error_message("zz = 3;");
+        assert!(message.contains("1:1"));
+        assert!(message.contains("variable was not declared: `zz`"));
+    }
+    #[test]
+    fn error_condition() {
+        let message = error_message("gimme a = 0;\nwhile \"s\" {}");
+        assert!(message.contains("2:7"));
+        assert!(message.contains("expected a value of type bool, found string"));
+    }
+    #[test]
+    fn error_kind() {
+        assert!(matches!(
+            run_code("gimme a = ;"),
+            Err(PenguinError::Parse { .. })
+        ));
+        assert!(matches!(
+            run_code("a = 1;"),
+            Err(PenguinError::Name {
+                error: NameError::NotDeclared,
+                ..
+            })
+        ));
+        assert!(matches!(
+            run_code("gimme a = 1;\ngimme a = 1;"),
+            Err(PenguinError::Name {
+                error: NameError::ReDeclaration,
+                ..
+            })
+        ));
+        assert!(matches!(
+            run_code("gimme a = 1 + [1];"),
+            Err(PenguinError::Type { .. })
+        ));
+        assert!(matches!(
+            run_code("gimme f = () => {};\nf(1);"),
+            Err(PenguinError::Arity { .. })
+        ));
+        assert!(matches!(
+            run_code("gimme a = [];\npop(a);"),
+            Err(PenguinError::Index { .. })
+        ));
+        assert!(matches!(
+            run_code("break;"),
+            Err(PenguinError::ControlFlow { .. })
+        ));
+    }
+    #[test]
+    fn error_code() {
+        let e = run_code("gimme a = 1;\nprintln(a + b);").unwrap_err();
+        assert_eq!(e.code(), "E0101");
+        assert_eq!(e.line_col(), Some((2, 13)));
+        assert_eq!(e.identifier(), Some("b"));
+        assert_eq!(e.message(), "variable was not declared: `b`");
+        let e = run_code("while false {\n}\ncontinue;").unwrap_err();
+        assert_eq!(e.code(), "E0602");
+        assert_eq!(e.line_col(), Some((3, 1)));
+        let e = run_code("gimme a = 1;\ngimme b = 1 +;").unwrap_err();
+        assert_eq!(e.code(), "E0001");
+        assert_eq!(e.line_col().map(|(line, _)| line), Some(2));
+        let e = run_code("gimme f = (a, b, a) => {};").unwrap_err();
+        assert_eq!(e.code(), "E0006");
+        assert_eq!(e.message(), "argument `a` is declared more than once");
+        let e = run_code("a: while false {\n    break b;\n}").unwrap_err();
+        assert_eq!(e.code(), "E0007");
+        assert_eq!(e.line_col(), Some((2, 11)));
+        assert_eq!(e.message(), "no loop labeled `b` encloses this statement");
+        let e = run_code("gimme a = 1;\ngimme b = if a { 1 };").unwrap_err();
+        assert_eq!(e.code(), "E0008");
+        assert_eq!(e.line_col(), Some((2, 11)));
+        let e = run_code("gimme f = (x) => {\n    gimme a = { return 1; 2 };\n};").unwrap_err();
+        assert_eq!(e.code(), "E0009");
+        assert_eq!(e.line_col(), Some((2, 17)));
+        assert_eq!(e.message(), "`return` cannot leave a block used as a value");
+        let e = run_code("gimme a = 1;\ngimme len = (x) => { return 42; };").unwrap_err();
+        assert_eq!(e.code(), "E0010");
+        assert_eq!(e.line_col(), Some((2, 7)));
+        assert_eq!(
+            e.message(),
+            "`len` is a builtin function and cannot be declared"
+        );
+        for code in [
+            "gimme f = (a, push) => {};",
+            "for keys in 0..1 {}",
+            "try {} catch (pop) {}",
+        ] {
+            assert_eq!(run_code(code).unwrap_err().code(), "E0010", "{}", code);
+        }
+        let e = run_code("gimme a = 1;\nthrow \"a\" + a;").unwrap_err();
+        assert_eq!(e.code(), "E1001");
+        assert_eq!(e.kind(), "throw");
+        assert_eq!(e.line_col(), Some((2, 1)));
+        assert_eq!(e.message(), "uncaught exception: \"a1\"");
+        let e = run_code("gimme a = \"x\";\nmatch a + 1 {\n    1 => {}\n}").unwrap_err();
+        assert_eq!(e.code(), "E0901");
+        assert_eq!(e.line_col(), Some((2, 7)));
+        assert_eq!(e.message(), "no arm of the match accepts \"x1\"");
+    }
+}
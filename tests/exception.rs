@@ -0,0 +1,240 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_output};
+
+    #[test]
+    fn catch_errors() {
+        test_code_output!(
+            "
+                try {
+                    gimme a = 1 / 0;
+                } catch (e) {
+                    println(e);
+                }
+                try {
+                    undefined + 1;
+                } catch (e) {
+                    println(e.kind + \" \" + e.message);
+                }
+                gimme f = (x) => {
+                    return x;
+                };
+                try {
+                    f(1, 2);
+                } catch (e) {
+                    println(e.code);
+                }
+                gimme a = [1];
+                try {
+                    a[5] = 1;
+                    println(\"unreachable\");
+                } catch (e) {
+                    a = e.kind;
+                }
+                println(a);
+            ",
+            "Error { kind: \"arithmetic\", code: \"E0402\", message: \"division by zero\" }\n\
+             name variable was not declared: `undefined`\n\
+             E0301\n\
+             index\n"
+        );
+    }
+    #[test]
+    fn throw_values() {
+        test_code_output!(
+            "
+                gimme check = (x) => {
+                    if x < 0 {
+                        throw \"negative: \" + x;
+                    }
+                    return x;
+                };
+                try {
+                    check(1);
+                    check(-2);
+                    check(3);
+                } catch (e) {
+                    println(e);
+                }
+                try {
+                    throw [1, 2];
+                } catch (e) {
+                    println(len(e));
+                }
+                try {
+                    try {
+                        throw 1;
+                    } catch (e) {
+                        throw e + 1;
+                    }
+                } catch (e) {
+                    println(e);
+                }
+            ",
+            "negative: -2\n2\n2\n"
+        );
+        test_code_failed!("throw 1;");
+        test_code_failed!("try {\n    throw 1;\n} finally {\n}");
+        test_code_failed!("try {\n    throw 1;\n} catch (e) {\n    throw e;\n}");
+    }
+    #[test]
+    fn finally_runs() {
+        test_code_output!(
+            "
+                try {
+                    print(1);
+                } finally {
+                    print(2);
+                }
+                try {
+                    throw 3;
+                } catch (e) {
+                    print(e);
+                } finally {
+                    print(4);
+                }
+                try {
+                    try {
+                        throw 6;
+                    } finally {
+                        print(5);
+                    }
+                } catch (e) {
+                    print(e);
+                }
+                try {
+                    try {
+                        throw 0;
+                    } catch (e) {
+                        gimme a = 1 / e;
+                    } finally {
+                        print(7);
+                    }
+                } catch (e) {
+                    println(e.code);
+                }
+            ",
+            "1234567E0402\n"
+        );
+    }
+    #[test]
+    fn finally_flow() {
+        test_code_output!(
+            "
+                gimme g = () => {
+                    for i in 0..10 {
+                        try {
+                            if i == 2 {
+                                return i * 100;
+                            }
+                        } finally {
+                            print(i);
+                        }
+                    }
+                    return -1;
+                };
+                println(g());
+                gimme h = () => {
+                    try {
+                        throw \"lost\";
+                    } finally {
+                        return \"kept\";
+                    }
+                };
+                println(h());
+                outer: for i in 0..2 {
+                    for j in 0..3 {
+                        try {
+                            try {
+                                if j == 1 {
+                                    continue outer;
+                                }
+                                print(i + \"\" + j);
+                            } finally {
+                                print(\"(\");
+                            }
+                        } finally {
+                            print(\")\");
+                        }
+                    }
+                }
+                println(\"\");
+                gimme n = 0;
+                while true {
+                    try {
+                        n += 1;
+                        if n == 3 {
+                            break;
+                        }
+                        throw n;
+                    } catch (e) {
+                        continue;
+                    } finally {
+                        print(n);
+                    }
+                }
+                println(\"\");
+            ",
+            "012200\nkept\n00()()10()()\n123\n"
+        );
+    }
+    #[test]
+    fn finally_nested_throw() {
+        //a value thrown and caught while the `finally` runs leaves the pending one alone
+        test_code_output!(
+            "
+                try {
+                    try {
+                        throw \"a\";
+                    } finally {
+                        try {
+                            throw \"b\";
+                        } catch (e) {
+                            print(e);
+                        }
+                    }
+                } catch (e) {
+                    println(e);
+                }
+                try {
+                    try {
+                        throw \"c\";
+                    } finally {
+                        throw \"d\";
+                    }
+                } catch (e) {
+                    println(e);
+                }
+            ",
+            "ba\nd\n"
+        );
+    }
+    #[test]
+    fn catch_scope() {
+        test_code_output!(
+            "
+                gimme fs = [];
+                for i in 0..3 {
+                    try {
+                        throw i;
+                    } catch (e) {
+                        push(fs, () => { return e; });
+                    }
+                }
+                println(fs[0]() + fs[1]() + fs[2]());
+                gimme e = 10;
+                try {
+                    throw 1;
+                } catch (e) {
+                    e += 1;
+                }
+                println(e);
+            ",
+            "3\n10\n"
+        );
+        test_code_failed!("try {\n} catch (e) {\n}\nprintln(e);");
+        //flow statements outside of their loop are not caught
+        test_code_failed!("try {\n    break;\n} catch (e) {\n}");
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok};
+
+    #[test]
+    fn float_literal() {
+        test_code_ok!(
+            "
+                gimme a = 1.5;
+                gimme b = 0.25e2;
+                gimme c = 3e-4;
+                gimme d = -2E+3;
+                println(a + b + c + d);
+            "
+        )
+    }
+    #[test]
+    fn float_mixed_arithmetic() {
+        test_code_ok!(
+            "
+                if 1 + 0.5 != 1.5 || 2 * 1.5 != 3 || 1 - 0.5 != 0.5 || 1 == 1.5 {
+                    undefined();
+                }
+                if 1.5 > 2 || 2 <= 1.5 || 1 != 1.0 {
+                    undefined();
+                }
+                gimme a = 1;
+                a += 0.5;
+                if a != 1.5 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn float_division() {
+        // integer division truncates toward zero, any float operand gives a float result
+        // and dividing a float by zero follows IEEE 754
+        test_code_ok!(
+            "
+                if 7 / 2 != 3 || -7 / 2 != -3 || 7.0 / 2 != 3.5 || 7 / 2.0 != 3.5 {
+                    undefined();
+                }
+                if 1.0 / 0 <= 1e308 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn float_modulo() {
+        // the remainder keeps the sign of the dividend for both ints and floats
+        test_code_ok!(
+            "
+                if 7 % 3 != 1 || -7 % 3 != -1 || 7.5 % 2 != 1.5 || -7.5 % 2 != -1.5 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn float_invalid_operation_should_fail() {
+        test_code_failed!(
+            "
+                gimme a = 1.5 & 1;
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = 1 << 1.0;
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = ~1.5;
+            "
+        );
+        test_code_failed!(
+            "
+                if 1.5 {}
+            "
+        );
+    }
+}
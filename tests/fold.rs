@@ -0,0 +1,87 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_output};
+    use penguin::{run_code_with_options, Options};
+
+    #[test]
+    fn fold_constants() {
+        test_code_output!(
+            "
+                gimme a = 2 * 3 + 4;
+                gimme b = (1 << 4) | 1;
+                gimme c = \"a\" + \"b\" + 1;
+                gimme d = !(1 > 2) && true;
+                gimme e = 9223372036854775807 + 1 - 1;
+                println([a, b, c, d, e]);
+            ",
+            "[10, 17, \"ab1\", true, 9223372036854775807]\n"
+        );
+    }
+    #[test]
+    fn fold_keeps_errors() {
+        test_code_failed!("gimme a = 1 / 0;");
+        test_code_failed!("gimme a = 1 << -1;");
+        test_code_failed!("gimme a = 1 + [1][1];");
+        //the error is raised when the statement runs, not before the program starts
+        for no_fold in [false, true] {
+            let e = run_code_with_options(
+                "gimme a = 1;\nwhile a > 2 {\n}\ngimme b = a + 1 / 0;",
+                Options {
+                    no_fold,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+            assert_eq!(e.code(), "E0402");
+            assert_eq!(e.line_col(), Some((4, 15)));
+        }
+    }
+    #[test]
+    fn fold_identities() {
+        test_code_output!(
+            "
+                gimme x = 7;
+                gimme s = \"a\";
+                gimme f = 1.5;
+                gimme b = false;
+                println([x + 0, 0 + x, x * 1, x - 0]);
+                println([s + 0, 0 + s, f * 1, !!b]);
+                println([!!x, true + 0]);
+            ",
+            "[7, 7, 7, 7]\n[\"a0\", \"0a\", 1.5, false]\n[true, 1]\n"
+        );
+        test_code_failed!("gimme a = [1] + 0;");
+        test_code_failed!("gimme a = [1];\ngimme b = !!a;");
+    }
+    #[test]
+    fn fold_chains() {
+        test_code_output!(
+            "
+                gimme i = 3;
+                gimme big = 9223372036854775807;
+                println([i * 2 * 1024, 2 * i * 4, i - 1 - 2 + 5]);
+                println([big * 2 * 4 - big * 8, big + 1 - 1 == big]);
+                gimme f = 0.1;
+                println([f + 1 + 2, f + 1 + 2 == 0.1 + 1 + 2]);
+                gimme s = \"s\";
+                println([s + 1 + 2, (i * 2) + 1]);
+            ",
+            "[6144, 24, 5]\n[0, true]\n[3.1, true]\n[\"s12\", 7]\n"
+        );
+    }
+    #[test]
+    fn fold_strict() {
+        for no_fold in [false, true] {
+            let options = Options {
+                strict: true,
+                no_fold,
+                ..Default::default()
+            };
+            assert!(run_code_with_options("gimme a = true + 1;", options).is_err());
+            assert!(run_code_with_options("gimme a = !!1;", options).is_err());
+            assert!(run_code_with_options("gimme b = true;\ngimme a = b * 1;", options).is_err());
+            assert!(run_code_with_options("gimme b = true;\ngimme a = !!b;", options).is_ok());
+        }
+    }
+}
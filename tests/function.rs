@@ -1,7 +1,7 @@
 mod common;
 #[cfg(test)]
 mod tests {
-    use crate::{test_code_failed, test_code_ok};
+    use crate::{test_code_failed, test_code_ok, test_code_output};
 
     #[test]
     fn function() {
@@ -68,4 +68,257 @@ mod tests {
             "
         );
     }
+    #[test]
+    fn function_ref_with_other_name() {
+        test_code_ok!(
+            "
+                gimme counter = 0;
+                gimme bump = (x) => {
+                    x += 1;
+                };
+                bump(&counter);
+                bump(&counter);
+                if counter != 2 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_sees_globals() {
+        test_code_ok!(
+            "
+                gimme base = 10;
+                gimme add_base = (x) => {
+                    return x + base;
+                };
+                base = 20;
+                if add_base(1) != 21 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme total = 0;
+                gimme add = (x) => {
+                    total += x;
+                };
+                add(2);
+                add(3);
+                if total != 5 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_recursion() {
+        test_code_ok!(
+            "
+                gimme fact = (n) => {
+                    if n <= 1 {
+                        return 1;
+                    }
+                    return n * fact(n - 1);
+                };
+                if fact(10) != 3628800 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme fib = (n) => {
+                    if n < 2 {
+                        return n;
+                    }
+                    return fib(n - 1) + fib(n - 2);
+                };
+                if fib(15) != 610 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_mutual_recursion() {
+        test_code_ok!(
+            "
+                gimme is_even = (n) => {
+                    if n == 0 {
+                        return true;
+                    }
+                    return is_odd(n - 1);
+                };
+                gimme is_odd = (n) => {
+                    if n == 0 {
+                        return false;
+                    }
+                    return is_even(n - 1);
+                };
+                if !is_even(10) || is_odd(10) || !is_odd(7) {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_calls_other_function() {
+        test_code_ok!(
+            "
+                gimme square = (x) => {
+                    return x * x;
+                };
+                gimme sum_of_squares = (a, b) => {
+                    return square(a) + square(b);
+                };
+                if sum_of_squares(3, 4) != 25 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_as_value() {
+        test_code_ok!(
+            "
+                gimme double = (x) => {
+                    return x * 2;
+                };
+                gimme f = double;
+                if f(4) != 8 || f != double {
+                    undefined();
+                }
+                f = (x) => {
+                    return x * 3;
+                };
+                if f(4) != 12 || f == double {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme ops = {\"add\": (a, b) => { return a + b; }, \"sub\": (a, b) => { return a - b; }};
+                gimme list = [ops[\"add\"], ops[\"sub\"]];
+                if list[0](5, 3) != 8 || ops[\"sub\"](5, 3) != 2 {
+                    undefined();
+                }
+                println(list);
+            "
+        );
+    }
+    #[test]
+    fn function_as_argument() {
+        test_code_ok!(
+            "
+                gimme apply = (f, x) => {
+                    return f(x);
+                };
+                gimme inc = (x) => {
+                    return x + 1;
+                };
+                if apply(inc, 1) != 2 {
+                    undefined();
+                }
+                if apply((x) => { return x * x; }, 5) != 25 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme map = (a, f) => {
+                    gimme res = [];
+                    gimme i = 0;
+                    while i < len(a) {
+                        push(res, f(a[i]));
+                        i += 1;
+                    }
+                    return res;
+                };
+                if map([1, 2, 3], (x) => { return x * 10; }) != [10, 20, 30] {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_as_return_value() {
+        test_code_ok!(
+            "
+                gimme pick = (add) => {
+                    if add {
+                        return (a, b) => { return a + b; };
+                    }
+                    return (a, b) => { return a * b; };
+                };
+                if pick(true)(3, 4) != 7 || pick(false)(3, 4) != 12 {
+                    undefined();
+                }
+                if (() => { return 1; })() != 1 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn function_call_non_function_should_fail() {
+        test_code_failed!(
+            "
+                gimme a = 1;
+                a();
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = [1];
+                a[0](2);
+            "
+        );
+        test_code_failed!(
+            "
+                gimme f = () => {};
+                f + 1;
+            "
+        );
+    }
+    #[test]
+    fn function_call_depth() {
+        //unoptimized builds use more stack per call than a test thread has for the whole limit
+        let test = || {
+            test_code_output!(
+                "
+                    gimme f = (n) => {
+                        return f(n + 1);
+                    };
+                    gimme g = (n) => {
+                        if n == 0 {
+                            return 0;
+                        }
+                        return g(n - 1) + 1;
+                    };
+                    try {
+                        f(0);
+                    } catch (e) {
+                        println(e.code);
+                    }
+                    println(g(999));
+                ",
+                "E0604\n999\n"
+            );
+            let e = penguin::run_code("gimme f = (n) => {\n    return f(n + 1);\n};\nf(0);")
+                .unwrap_err();
+            assert_eq!(e.code(), "E0604");
+            assert_eq!(e.line_col(), Some((2, 12)));
+            assert_eq!(e.message(), "calls are nested more than 1000 deep: `f`");
+        };
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
 }
@@ -1,7 +1,8 @@
 mod common;
 #[cfg(test)]
 mod tests {
-    use crate::test_code_ok;
+    use crate::{test_code_failed, test_code_ok, test_code_output};
+    use penguin::{run_code, run_code_with_options, Options};
 
     #[test]
     fn while_loop() {
@@ -14,4 +15,188 @@ mod tests {
             "
         )
     }
+    #[test]
+    fn for_loop() {
+        test_code_output!(
+            "
+                for i in 0..3 {
+                    print(i);
+                }
+                for i in 1..=3 {
+                    print(i);
+                }
+                for i in 0..=10 step 4 {
+                    print(i);
+                }
+                for i in 3..0 step -1 {
+                    print(i);
+                }
+                for i in 3..3 {
+                    print(i);
+                }
+                for i in 3..0 {
+                    print(i);
+                }
+                gimme n = 2;
+                for i in n - 1..n * 2 step n {
+                    print(i);
+                }
+            ",
+            "01212304832113"
+        );
+        test_code_output!(
+            "
+                for i in 9223372036854775806..=9223372036854775807 {
+                    println(i);
+                }
+                for i in -9223372036854775807..-9223372036854775807 - 1 step -1 {
+                    println(i);
+                }
+            ",
+            "9223372036854775806\n9223372036854775807\n-9223372036854775807\n"
+        );
+    }
+    #[test]
+    fn for_loop_flow() {
+        test_code_output!(
+            "
+                for i in 0..10 {
+                    if i == 1 {
+                        continue;
+                    }
+                    if i == 4 {
+                        break;
+                    }
+                    for j in 0..10 {
+                        if j > i {
+                            break;
+                        }
+                        print(j);
+                    }
+                    print(\",\");
+                }
+                gimme find = (n) => {
+                    for i in 1..n {
+                        for j in 1..n {
+                            if i * j == n {
+                                return [i, j];
+                            }
+                        }
+                    }
+                    return [];
+                };
+                print(find(12));
+                print(find(1));
+            ",
+            "0,012,0123,[2, 6][]"
+        );
+    }
+    #[test]
+    fn for_loop_scope() {
+        //the loop variable only lives in the loop and every iteration has its own,
+        //assigning to it does not change the next value
+        test_code_output!(
+            "
+                gimme i = 10;
+                gimme fs = [];
+                for i in 0..3 {
+                    push(fs, () => { return i; });
+                    i = 5;
+                }
+                for i in 0..3 {
+                    gimme j = i * 2;
+                    print(j);
+                }
+                print(i);
+                print(fs[0]() + fs[1]() + fs[2]());
+            ",
+            "0241015"
+        );
+        test_code_failed!("for i in 0..3 {\n    gimme i = 1;\n}");
+        test_code_failed!("for i in 0..3 {\n}\nprint(i);");
+    }
+    #[test]
+    fn for_loop_errors() {
+        let e = run_code("gimme a = 1.5;\nfor i in 0..a {\n}").unwrap_err();
+        assert_eq!(e.code(), "E0201");
+        assert_eq!(e.line_col(), Some((2, 13)));
+        let e = run_code("gimme a = 0;\nfor i in 0..3 step a {\n}").unwrap_err();
+        assert_eq!(e.code(), "E0405");
+        assert_eq!(e.line_col(), Some((2, 20)));
+        test_code_failed!("for i in 0..3 step 0 {\n}");
+        test_code_failed!("for i in 0..9223372036854775808 {\n}");
+        test_code_failed!("for i in 0..(1 / 0) {\n}");
+        test_code_ok!("for i in true..2 {\n}");
+        let strict = Options {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(run_code_with_options("for i in true..2 {\n}", strict).is_err());
+        assert!(run_code_with_options("for i in 0..2 {\n}", strict).is_ok());
+    }
+    #[test]
+    fn labeled_loop() {
+        test_code_output!(
+            "
+                outer: for i in 0..5 {
+                    inner: for j in 0..5 {
+                        if j == 3 {
+                            continue outer;
+                        }
+                        if i == 3 {
+                            break outer;
+                        }
+                        if j > i {
+                            continue inner;
+                        }
+                        print(i * 10 + j);
+                        print(\" \");
+                    }
+                }
+                gimme n = 0;
+                rows: while n < 10 {
+                    n += 1;
+                    gimme k = 0;
+                    while true {
+                        k += 1;
+                        gimme g = () => { return k; };
+                        if g() == 2 {
+                            continue rows;
+                        }
+                        if n == 4 {
+                            break rows;
+                        }
+                    }
+                }
+                print(n);
+            ",
+            "0 10 11 20 21 22 4"
+        );
+        //the innermost loop with the label is the target
+        test_code_output!(
+            "
+                a: for i in 0..2 {
+                    a: for j in 0..3 {
+                        if j == 1 {
+                            break a;
+                        }
+                        print(j);
+                    }
+                    print(i);
+                }
+            ",
+            "0001"
+        );
+    }
+    #[test]
+    fn labeled_loop_unknown() {
+        test_code_failed!("while false {\n    break a;\n}");
+        test_code_failed!("a: while false {\n}\nwhile false {\n    continue a;\n}");
+        //labels don't reach into functions
+        test_code_failed!("a: while false {\n    gimme f = () => {\n        break a;\n    };\n}");
+        test_code_failed!("break a;");
+        //a statement starting like `break` is still an expression
+        test_code_failed!("breaka;");
+        test_code_ok!("gimme breaka = 1;\nbreaka;");
+    }
 }
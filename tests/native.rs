@@ -0,0 +1,132 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::common::engines;
+    use penguin::{parser::node::primitive::Primitive, Interpreter, Options, PenguinError};
+
+    fn interpreters() -> Vec<Interpreter> {
+        engines()
+            .into_iter()
+            .map(|options| {
+                Interpreter::new()
+                    .options(options)
+                    .function("add", |a: i64, b: i64| a + b)
+                    .function("greet", |name: String| format!("hello {}", name))
+                    .function("half", |v: f64| v / 2.0)
+                    .function("root", |v: f64| match v >= 0.0 {
+                        true => Ok(v.sqrt()),
+                        false => Err(format!("{} has no square root", v)),
+                    })
+            })
+            .collect()
+    }
+
+    fn run_all(code: &str) -> Vec<Result<(), PenguinError>> {
+        interpreters().iter().map(|v| v.run(code)).collect()
+    }
+
+    #[test]
+    fn native_call() {
+        let code = "
+            gimme f = add;
+            gimme g = (x) => {
+                return add(x, f(1, 1)) * half(4);
+            };
+            println([g(3), greet(\"penguin\"), root(9)]);
+            gimme a = 1;
+            println([add(&a, true), a]);
+        ";
+        for interpreter in interpreters() {
+            assert_eq!(
+                interpreter.run_to_string(code).unwrap(),
+                "[10.0, \"hello penguin\", 3.0]\n[2, 1]\n"
+            );
+        }
+    }
+    #[test]
+    fn native_errors() {
+        let cases = [
+            ("gimme a = 1;\ngimme b = root(a - 5);", "E0801", (2, 11)),
+            ("gimme a = add(1);", "E0301", (1, 11)),
+            ("gimme a = 1;\ngimme b = greet(a);", "E0201", (2, 11)),
+            (
+                "gimme a = add(9223372036854775807 + 1, 1);",
+                "E0404",
+                (1, 11),
+            ),
+        ];
+        for (code, error, line_col) in cases {
+            for res in run_all(code) {
+                let e = res.unwrap_err();
+                assert_eq!(e.code(), error, "{}", code);
+                assert_eq!(e.line_col(), Some(line_col), "{}", code);
+            }
+        }
+        let e = run_all("root(-4.0);").remove(0).unwrap_err();
+        assert!(e.message().contains("-4 has no square root"));
+    }
+    #[test]
+    fn native_strict() {
+        for options in engines() {
+            let interpreter = Interpreter::new()
+                .function("double", |a: i64| a * 2)
+                .function("neg", |b: bool| !b)
+                .function("half", |v: f64| v / 2.0);
+            let code = "println([double(true), neg(-1), half(false)]);";
+            assert_eq!(
+                interpreter.options(options).run_to_string(code).unwrap(),
+                "[2, true, 0.0]\n"
+            );
+        }
+        for options in engines() {
+            let interpreter = Interpreter::new()
+                .options(Options {
+                    strict: true,
+                    ..options
+                })
+                .function("double", |a: i64| a * 2)
+                .function("neg", |b: bool| !b)
+                .function("half", |v: f64| v / 2.0);
+            for code in ["double(true);", "neg(-1);", "half(false);"] {
+                let e = interpreter.run(code).unwrap_err();
+                assert_eq!(e.code(), "E0201", "{}", code);
+                assert_eq!(e.line_col(), Some((1, 1)), "{}", code);
+            }
+            assert_eq!(
+                interpreter
+                    .run_to_string("println([double(2), neg(false), half(1)]);")
+                    .unwrap(),
+                "[4, true, 0.5]\n"
+            );
+        }
+    }
+    #[test]
+    fn native_state() {
+        //every run of a program calls the same closure
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let record = Rc::clone(&seen);
+        let interpreter = Interpreter::new().function("record", move |v: Primitive| {
+            record.borrow_mut().push(v.to_string());
+        });
+        let program = interpreter
+            .compile("gimme i = 0;\nwhile i < 2 {\n    record([i]);\n    i += 1;\n}")
+            .unwrap();
+        program.run().unwrap();
+        program.run().unwrap();
+        assert_eq!(*seen.borrow(), ["[0]", "[1]", "[0]", "[1]"]);
+    }
+    #[test]
+    fn native_builtin_name() {
+        let e = Interpreter::new()
+            .function("len", |_: Primitive| 42)
+            .run("println(len([1]));")
+            .unwrap_err();
+        assert_eq!(e.code(), "E0010");
+        assert_eq!(
+            e.message(),
+            "`len` is a builtin function and cannot be declared"
+        );
+    }
+}
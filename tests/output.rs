@@ -0,0 +1,66 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use crate::{common::engines, test_code_output};
+    use penguin::compile_with_options;
+
+    #[test]
+    fn output_values() {
+        test_code_output!(
+            r#"
+                print(1);
+                print(" ");
+                println(2.0);
+                println([1, "a", [true]]);
+                println("line");
+                gimme f = (a, b) => { return a; };
+                println(f);
+            "#,
+            "1 2.0\n[1, \"a\", [true]]\nline\n(a, b) => {...}\n"
+        );
+        test_code_output!("", "");
+    }
+    #[test]
+    fn output_before_error() {
+        for options in engines() {
+            let mut output = Vec::new();
+            let code = "println(1);\ngimme a = 1 / 0;\nprintln(2);";
+            let mut program = compile_with_options(code, options).unwrap();
+            program.set_output(&mut output);
+            assert_eq!(program.run().unwrap_err().code(), "E0402");
+            //captured output does not reach the writer
+            assert!(program.run_to_string().is_err());
+            program.run().unwrap_err();
+            drop(program);
+            assert_eq!(output, b"1\n1\n");
+        }
+    }
+
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "output closed"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn output_write_error() {
+        for options in engines() {
+            let mut program =
+                compile_with_options("gimme a = 1;\nprintln(a);\ngimme b = 2;", options).unwrap();
+            program.set_output(Closed);
+            let e = program.run().unwrap_err();
+            assert_eq!(e.code(), "E0701");
+            assert_eq!(e.line_col(), Some((2, 1)));
+            assert!(e.message().contains("output closed"));
+            //the output can be captured again after a failure
+            assert_eq!(program.run_to_string().as_deref().ok(), Some("1\n"));
+        }
+    }
+}
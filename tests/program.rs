@@ -0,0 +1,58 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::common::engines;
+    use penguin::{compile, compile_with_options, Program};
+
+    fn programs(code: &str) -> Vec<Program<'_>> {
+        engines()
+            .into_iter()
+            .map(|options| compile_with_options(code, options).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn program_runs_again() {
+        //every run declares the globals again and starts with a fresh array
+        let code = "
+            gimme a = [1, 2];
+            gimme count = () => {
+                push(a, 3);
+                return len(a);
+            };
+            if count() != 3 {
+                undefined();
+            }
+        ";
+        for program in programs(code) {
+            for _ in 0..3 {
+                program.run().unwrap();
+            }
+        }
+    }
+    #[test]
+    fn program_runs_after_error() {
+        let code = "
+            gimme f = (x) => {
+                { gimme y = x; return 1 / y; }
+            };
+            gimme i = 1;
+            while i >= 0 {
+                f(i);
+                i -= 1;
+            }
+        ";
+        for program in programs(code) {
+            for _ in 0..2 {
+                let e = program.run().unwrap_err();
+                assert_eq!(e.code(), "E0402");
+                assert_eq!(e.line_col(), Some((3, 39)));
+            }
+        }
+    }
+    #[test]
+    fn program_parse_error() {
+        assert_eq!(compile("gimme a = ;").err().unwrap().code(), "E0001");
+        assert!(compile("gimme a = 1;").is_ok());
+    }
+}
@@ -1,7 +1,7 @@
 mod common;
 #[cfg(test)]
 mod tests {
-    use crate::{test_code_failed, test_code_ok};
+    use crate::{test_code_failed, test_code_ok, test_code_output};
 
     #[test]
     fn empty_scope() {
@@ -45,4 +45,186 @@ mod tests {
             "
         );
     }
+    #[test]
+    fn scope_function_is_lexical() {
+        test_code_ok!(
+            "
+                gimme outer = (x) => {
+                    gimme y = x * 2;
+                    gimme inner = () => {
+                        return x + y;
+                    };
+                    return inner();
+                };
+                if outer(3) != 9 {
+                    undefined();
+                }
+            "
+        );
+        test_code_ok!(
+            "
+                gimme a = 1;
+                gimme read = () => {
+                    return a;
+                };
+                gimme shadow = () => {
+                    gimme a = 2;
+                    return read();
+                };
+                if shadow() != 1 {
+                    undefined();
+                }
+            "
+        );
+    }
+    #[test]
+    fn scope_caller_locals_are_hidden() {
+        test_code_failed!(
+            "
+                gimme read = () => {
+                    return hidden;
+                };
+                gimme call = () => {
+                    gimme hidden = 1;
+                    return read();
+                };
+                call();
+            "
+        );
+    }
+    #[test]
+    fn scope_function_locals_are_dropped() {
+        test_code_failed!(
+            "
+                gimme f = () => {
+                    gimme local = 1;
+                };
+                f();
+                local = 2;
+            "
+        );
+        test_code_ok!(
+            "
+                gimme f = (x) => {
+                    gimme local = x;
+                    return local;
+                };
+                f(1);
+                f(2);
+            "
+        );
+    }
+    #[test]
+    fn scope_function_sees_later_declarations() {
+        test_code_ok!(
+            "
+                gimme outer = (n) => {
+                    gimme is_even = (n) => {
+                        if n == 0 {
+                            return true;
+                        }
+                        return is_odd(n - 1);
+                    };
+                    gimme is_odd = (n) => {
+                        if n == 0 {
+                            return false;
+                        }
+                        return is_even(n - 1);
+                    };
+                    return is_even(n);
+                };
+                if !outer(10) || outer(7) {
+                    undefined();
+                }
+            "
+        );
+        test_code_failed!(
+            "
+                gimme outer = () => {
+                    gimme f = () => {
+                        return later;
+                    };
+                    f();
+                    gimme later = 1;
+                };
+                outer();
+            "
+        );
+    }
+    #[test]
+    fn scope_block_redeclare() {
+        test_code_ok!(
+            "
+                gimme f = (x) => {
+                    gimme i = 0;
+                    while i < 3 {
+                        gimme x = i;
+                        {
+                            gimme x = x + 1;
+                            i = x;
+                        }
+                    }
+                    return x;
+                };
+                if f(5) != 5 {
+                    undefined();
+                }
+            "
+        );
+        test_code_failed!(
+            "
+                gimme f = (x) => {
+                    gimme x = 1;
+                };
+                f(1);
+            "
+        );
+        test_code_failed!(
+            "
+                gimme f = () => {
+                    if true {
+                        gimme a = 1;
+                        gimme a = 2;
+                    }
+                };
+                f();
+            "
+        );
+        test_code_failed!("gimme f = (a, a) => {};");
+    }
+    #[test]
+    fn block_expression() {
+        test_code_output!(
+            "
+                gimme a = 3;
+                gimme b = {
+                    gimme a = a * 2;
+                    a + 1
+                };
+                println(a + \" \" + b);
+                gimme fs = [];
+                gimme total = {
+                    gimme t = 0;
+                    outer: for i in 0..5 {
+                        while true {
+                            if i == 3 {
+                                continue outer;
+                            }
+                            break;
+                        }
+                        t += i;
+                        push(fs, () => { return i; });
+                    }
+                    t
+                };
+                println(total + \" \" + fs[2]() + \" \" + { [a] });
+            ",
+            "3 7\n7 2 [3]\n"
+        );
+        test_code_failed!("gimme a = { gimme b = 1; b } + b;");
+        test_code_failed!("while true {\n    gimme a = { break; 1 };\n}");
+        test_code_failed!(
+            "outer: while true {\n    gimme a = { while true { break outer; } 1 };\n}"
+        );
+    }
 }
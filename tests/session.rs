@@ -0,0 +1,122 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::common::engines;
+    use penguin::{
+        session::{is_incomplete, Session},
+        Options,
+    };
+
+    //a session on each engine
+    fn sessions(strict: bool) -> [Session; 2] {
+        engines().map(|options| Session::new(Options { strict, ..options }))
+    }
+
+    fn value(session: &mut Session, code: &str) -> Option<String> {
+        session.run(code).unwrap().map(|v| v.to_string())
+    }
+
+    #[test]
+    fn session_keeps_state() {
+        for mut session in sessions(false) {
+            assert_eq!(value(&mut session, "gimme a = 20;"), None);
+            assert_eq!(value(&mut session, "a + 1"), Some("21".to_string()));
+            assert_eq!(
+                value(&mut session, "gimme f = (x) => {\n  return x * a;\n}"),
+                None
+            );
+            assert_eq!(value(&mut session, "a = 2"), None);
+            assert_eq!(value(&mut session, "f(5);"), Some("10".to_string()));
+            assert_eq!(value(&mut session, "struct P { x }"), None);
+            assert_eq!(
+                value(&mut session, "gimme p = P { x: a };\np"),
+                Some("P { x: 2 }".to_string())
+            );
+            assert_eq!(value(&mut session, "println(p.x)"), None);
+        }
+    }
+    #[test]
+    fn session_later_globals() {
+        for mut session in sessions(false) {
+            session
+                .run("gimme g = () => {\n  return later;\n};")
+                .unwrap();
+            assert!(session.run("g()").is_err());
+            session.run("gimme later = 3;").unwrap();
+            assert_eq!(value(&mut session, "g()"), Some("3".to_string()));
+            assert!(session.run("gimme later = 4;").is_err());
+        }
+    }
+    #[test]
+    fn session_if_value() {
+        for mut session in sessions(false) {
+            assert_eq!(
+                value(&mut session, "if true { 1 } else { 2 }"),
+                Some("1".to_string())
+            );
+            assert_eq!(
+                value(&mut session, "{ gimme a = 2; a * 3 }"),
+                Some("6".to_string())
+            );
+            assert_eq!(value(&mut session, "if true {\n  1;\n}"), None);
+        }
+    }
+    #[test]
+    fn session_error_keeps_state() {
+        for mut session in sessions(false) {
+            session.run("gimme a = 1;").unwrap();
+            assert!(session.run("a = 2; a = a / 0; a = 3;").is_err());
+            assert_eq!(value(&mut session, "a"), Some("2".to_string()));
+            assert!(session.run("gimme b = 1 +").is_err());
+            assert!(session.run("b").is_err());
+            session
+                .run("gimme f = (x) => { { gimme y = x; return y / 0; } }")
+                .unwrap();
+            assert!(session.run("while true { gimme c = 1; f(c); }").is_err());
+            assert!(session.run("c").is_err());
+            assert_eq!(
+                value(&mut session, "gimme c = 4;\nc + a"),
+                Some("6".to_string())
+            );
+        }
+    }
+    #[test]
+    fn session_error_location() {
+        for mut session in sessions(false) {
+            session.run("gimme a = 0;").unwrap();
+            session
+                .run("gimme f = () => {\n  return 1 / a;\n};")
+                .unwrap();
+            let e = session.run("f()").unwrap_err();
+            assert_eq!(e.code(), "E0402");
+            assert_eq!(e.line_col(), Some((3, 10)));
+            let e = session.run("\n\ng").unwrap_err();
+            assert_eq!(e.line_col(), Some((8, 1)));
+            assert_eq!(e.identifier(), Some("g"));
+            let e = session.run("gimme 1").unwrap_err();
+            assert_eq!(e.code(), "E0001");
+            assert_eq!(e.line_col().map(|(line, _)| line), Some(9));
+            session.run("gimme b = [1];").unwrap();
+            let e = session.run("b[5]").unwrap_err();
+            assert_eq!(e.code(), "E0501");
+            assert_eq!(e.line_col(), Some((11, 1)));
+        }
+    }
+    #[test]
+    fn session_strict() {
+        for mut session in sessions(true) {
+            assert!(session.run("1 + true").is_err());
+            assert_eq!(value(&mut session, "1 + 1"), Some("2".to_string()));
+        }
+    }
+    #[test]
+    fn session_incomplete() {
+        assert!(is_incomplete("gimme f = () => {"));
+        assert!(is_incomplete("if a {\n  gimme b = [1,\n"));
+        assert!(is_incomplete("println((1 + 2)"));
+        assert!(!is_incomplete("gimme f = () => {\n};"));
+        assert!(!is_incomplete("gimme s = \"{\";"));
+        assert!(!is_incomplete("gimme s = \"\\\"{\";"));
+        assert!(!is_incomplete("}"));
+    }
+}
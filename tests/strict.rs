@@ -0,0 +1,114 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::test_code_ok;
+    use penguin::{error::TypeError, run_code_with_options, Options, PenguinError};
+
+    //both engines have to fail the same way, with and without folding
+    fn run_strict(code: &str) -> Result<(), PenguinError> {
+        let run = |vm, no_fold| {
+            run_code_with_options(
+                code,
+                Options {
+                    strict: true,
+                    vm,
+                    no_fold,
+                },
+            )
+        };
+        let res = run(false, false);
+        for (vm, no_fold) in [(false, true), (true, false), (true, true)] {
+            assert_eq!(
+                res.as_ref().map_err(|e| e.to_string()),
+                run(vm, no_fold).as_ref().map_err(|e| e.to_string())
+            );
+        }
+        res
+    }
+
+    #[test]
+    fn strict_arithmetic_on_bool() {
+        let code = "gimme a = true + 1;";
+        test_code_ok!(code);
+        let e = run_strict(code).unwrap_err();
+        assert!(matches!(
+            e,
+            PenguinError::Type {
+                error: TypeError::UnsupportedOperation { .. },
+                ..
+            }
+        ));
+        assert_eq!(e.line_col(), Some((1, 11)));
+        assert!(run_strict("gimme a = 1 < false;").is_err());
+        assert!(run_strict("gimme a = true & true;").is_err());
+        assert!(run_strict("gimme a = -true;").is_err());
+        assert!(run_strict("gimme a = ~false;").is_err());
+        assert!(run_strict("gimme a = 1;\na += true;").is_err());
+        assert!(run_strict("gimme a = [1];\na[0] *= false;").is_err());
+        assert!(run_strict("gimme a = 1 + 2 * 3 - 4 / 2;").is_ok());
+        assert!(run_strict("gimme a = \"a is \" + true;").is_ok());
+    }
+    #[test]
+    fn strict_condition_on_int() {
+        test_code_ok!("if 1 { }");
+        test_code_ok!("if -1 { undefined(); }");
+        let e = run_strict("gimme a = 1;\nif a {\n}").unwrap_err();
+        assert_eq!(e.code(), "E0201");
+        assert_eq!(e.line_col(), Some((2, 4)));
+        assert_eq!(e.identifier(), Some("a"));
+        assert!(run_strict("gimme a = 0;\nwhile a {\n}").is_err());
+        assert!(run_strict("gimme a = 1 && true;").is_err());
+        assert!(run_strict("gimme a = false || 1;").is_err());
+        assert!(run_strict("gimme a = !1;").is_err());
+        assert!(run_strict(
+            "
+                gimme a = 0;
+                while a < 3 && !false {
+                    if a == 1 || false {
+                        a += 1;
+                    }
+                    a += 1;
+                }
+            "
+        )
+        .is_ok());
+    }
+    #[test]
+    fn strict_cross_type_equality() {
+        test_code_ok!(
+            "
+                if true != 1 || 1 != 1.0 || 1 == \"1\" {
+                    undefined();
+                }
+            "
+        );
+        let e = run_strict("gimme a = true == 1;").unwrap_err();
+        assert_eq!(e.code(), "E0202");
+        assert_eq!(e.line_col(), Some((1, 11)));
+        assert!(run_strict("gimme a = 1 != 1.0;").is_err());
+        assert!(run_strict("gimme a = 1 == \"1\";").is_err());
+        assert!(run_strict("gimme a = [1] == {};").is_err());
+        assert!(run_strict(
+            "
+                gimme a = true == false;
+                gimme b = 1 != 2 && 9223372036854775808 != 1 && 1.0 == 1.0;
+                gimme c = [1, 2] == [1, 2] && \"a\" != \"b\";
+            "
+        )
+        .is_ok());
+    }
+    #[test]
+    fn strict_bool_index() {
+        let e = run_strict("gimme a = [1, 2];\nprintln(a[true]);").unwrap_err();
+        assert!(matches!(
+            e,
+            PenguinError::Type {
+                error: TypeError::TypeMismatch { .. },
+                ..
+            }
+        ));
+        assert_eq!(e.line_col(), Some((2, 9)));
+        assert!(run_strict("gimme a = [1, 2];\na[false] = 3;").is_err());
+        assert!(run_strict("gimme a = [1, 2];\na[1] = 3;\nprintln(a[0]);").is_ok());
+    }
+}
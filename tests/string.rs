@@ -0,0 +1,83 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok, test_code_output};
+
+    #[test]
+    fn string_literal() {
+        test_code_ok!(
+            r#"
+                gimme a = "";
+                gimme b = "hello world";
+                gimme c = "tab\t newline\n quote\" backslash\\ # not a comment";
+                println(b);
+            "#
+        )
+    }
+    #[test]
+    fn string_invalid_escape_should_fail() {
+        test_code_failed!(
+            r#"
+                gimme a = "\q";
+            "#
+        );
+        test_code_failed!(
+            r#"
+                gimme a = "unterminated;
+            "#
+        );
+    }
+    #[test]
+    fn string_concatenation() {
+        test_code_output!(
+            r#"
+                gimme a = "count: " + 3;
+                gimme b = 3 + " apples" + true;
+                a += "!";
+                println(a + b);
+            "#,
+            "count: 3!3 applestrue\n"
+        )
+    }
+    #[test]
+    fn string_comparison() {
+        test_code_ok!(
+            r#"
+                if "abc" != "abc" || "abc" >= "abd" || "a" == 1 {
+                    undefined();
+                }
+            "#
+        )
+    }
+    #[test]
+    fn string_invalid_operation_should_fail() {
+        test_code_failed!(
+            r#"
+                gimme a = "abc" - 1;
+            "#
+        );
+        test_code_failed!(
+            r#"
+                gimme a = "abc" < 1;
+            "#
+        );
+        test_code_failed!(
+            r#"
+                if "abc" {}
+            "#
+        );
+    }
+    #[test]
+    fn string_function() {
+        test_code_ok!(
+            r#"
+                gimme greet = (name) => {
+                    return "hello, " + name;
+                };
+                if greet("penguin") != "hello, penguin" {
+                    undefined();
+                }
+            "#
+        )
+    }
+}
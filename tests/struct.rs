@@ -0,0 +1,126 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::{test_code_failed, test_code_ok, test_code_output};
+
+    #[test]
+    fn struct_declaration() {
+        test_code_ok!(
+            "
+                struct Point { x, y }
+                struct Edge {
+                    from,
+                    to,
+                    weight,
+                }
+                gimme a = Point { y: 2, x: 1 };
+                println(a);
+            "
+        )
+    }
+    #[test]
+    fn struct_field_access() {
+        test_code_ok!(
+            "
+                struct Interval { lo, hi }
+                gimme a = Interval { lo: 1, hi: 5 };
+                a.lo = 2;
+                a.hi *= 2;
+                if a.hi - a.lo != 8 || a != Interval { lo: 2, hi: 10 } {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn struct_nested_access() {
+        test_code_ok!(
+            "
+                struct Point { x, y }
+                struct Edge { from, to }
+                gimme a = [Edge { from: Point { x: 0, y: 0 }, to: Point { x: 1, y: 1 } }];
+                a[0].to.x += 5;
+                if a[0].to.x != 6 || a[0].from.y != 0 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn struct_is_hoisted() {
+        test_code_ok!(
+            "
+                gimme make = (x) => {
+                    return Wrapper { value: x };
+                };
+                struct Wrapper { value }
+                if make(1).value != 1 {
+                    undefined();
+                }
+            "
+        )
+    }
+    #[test]
+    fn struct_invalid_field_should_fail() {
+        test_code_failed!(
+            "
+                struct Point { x, y }
+                gimme a = Point { x: 1 };
+            "
+        );
+        test_code_failed!(
+            "
+                struct Point { x, y }
+                gimme a = Point { x: 1, y: 2, z: 3 };
+            "
+        );
+        test_code_failed!(
+            "
+                struct Point { x, y }
+                gimme a = Point { x: 1, y: 2 };
+                println(a.z);
+            "
+        );
+        test_code_failed!(
+            "
+                struct Point { x, y }
+                gimme a = Point { x: 1, y: 2 };
+                a.z = 1;
+            "
+        );
+        test_code_failed!(
+            "
+                gimme a = Point { x: 1, y: 2 };
+            "
+        );
+    }
+    #[test]
+    fn struct_redeclare_should_fail() {
+        test_code_failed!(
+            "
+                struct Point { x, y }
+                struct Point { x }
+            "
+        );
+        test_code_failed!(
+            "
+                struct Point { x, x }
+            "
+        );
+    }
+    #[test]
+    fn struct_contains_itself() {
+        test_code_output!(
+            "
+                struct Node { value, next }
+                gimme a = Node { value: 1, next: 0 };
+                a.next = a;
+                gimme b = Node { value: 1, next: 0 };
+                b.next = b;
+                println(a);
+                println(a == b);
+            ",
+            "Node { value: 1, next: Node {...} }\ntrue\n"
+        );
+    }
+}
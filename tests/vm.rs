@@ -0,0 +1,104 @@
+mod common;
+#[cfg(test)]
+mod tests {
+    use crate::test_code_ok;
+    use penguin::{run_code_with_options, Options, PenguinError};
+
+    fn run_vm(code: &str) -> Result<(), PenguinError> {
+        run_code_with_options(
+            code,
+            Options {
+                vm: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    //the vm has to report an error with the same kind and at the same place as the tree walker
+    fn same_error(code: &str) {
+        let e = run_code_with_options(code, Options::default()).unwrap_err();
+        let vm = run_vm(code).unwrap_err();
+        assert_eq!(e.to_string(), vm.to_string());
+        assert_eq!(e.code(), vm.code());
+        assert_eq!(e.line_col(), vm.line_col());
+        assert_eq!(e.identifier(), vm.identifier());
+    }
+
+    #[test]
+    fn vm_error_location() {
+        same_error("gimme a = 1;\nprintln(a + b);");
+        same_error("gimme f = (x) => {\n    return x;\n};\nf(1, 2);");
+        same_error("gimme f = () => {\n    gimme x = [1];\n    return x[3];\n};\nf();");
+        same_error("gimme a = 1;\ngimme a = 2;");
+        same_error("zz = 3;");
+        same_error("gimme a = 0;\nwhile \"s\" {}");
+        same_error("gimme a = 1;\ngimme b = a / (a - 1);");
+        same_error("gimme a = 1;\n\na <<= -64;");
+        same_error("gimme a = [1];\nprint(a[1 << 64]);");
+        same_error("gimme a = [];\npop(a);");
+        same_error("gimme a = 1;\na(2);");
+        same_error("gimme a = [1];\na[0].x = 2;");
+        same_error("struct P { x }\ngimme p = P { x: 1, y: 2 };");
+        same_error("gimme a = 1.5;\nfor i in 0..a {\n}");
+        same_error("for i in 0..3 step 3 - 3 {\n}");
+        same_error("for i in 0..3 {\n    gimme j = i / (i - 2);\n}");
+        same_error("gimme a = 4;\nmatch a * 2 {\n    0..8 => {}\n}");
+        same_error("match 1 {\n    1 if 2 / 0 => {}\n}");
+        same_error("gimme a = 0;\ngimme b = if a { 1 } else {\n    gimme c = [];\n    c[a]\n};");
+        same_error("gimme a = {\n    gimme b = 2;\n    b / 0\n} + 1;");
+        same_error("try {\n    throw 1;\n} catch (e) {\n    gimme a = e / 0;\n}");
+        same_error("try {\n    throw 1;\n} finally {\n    gimme a = [];\n    a[1];\n}");
+        same_error(
+            "gimme f = () => {\n    try {\n        throw 2;\n    } finally {\n    }\n};\nf();",
+        );
+    }
+    #[test]
+    fn vm_flow_error() {
+        same_error("while false {\n}\ncontinue;");
+        same_error("break;");
+        same_error("return 1 / 0;");
+        same_error("gimme f = () => {\n    break;\n};\nf();");
+        same_error("gimme f = () => {\n    while true {\n        continue 1;\n    }\n};");
+        same_error("for i in 0..3 {\n    return i;\n}");
+        same_error("try {\n    break;\n} catch (e) {\n} finally {\n    print(1);\n}");
+        same_error("gimme f = () => {\n    try {\n        break;\n    } catch (e) {\n    }\n};\ntry {\n    f();\n} catch (e) {\n}");
+    }
+    #[test]
+    fn vm_scopes_after_error() {
+        //a function failing inside of blocks and loops leaves no variable behind
+        test_code_ok!(
+            "
+                gimme f = (x) => {
+                    { gimme y = x; return y / x; }
+                };
+                gimme g = () => {
+                    gimme i = 0;
+                    while i < 3 {
+                        gimme v = f(i);
+                        i += 1;
+                    }
+                };
+                gimme h = (x) => {
+                    return x;
+                };
+                gimme i = h(4);
+            "
+        );
+    }
+    #[test]
+    fn vm_references() {
+        test_code_ok!(
+            "
+                gimme a = 1;
+                gimme f = (x, y) => {
+                    x = x + y;
+                    return () => { return y; };
+                };
+                gimme g = f(&a, 2);
+                if a != 3 || g() != 2 {
+                    undefined();
+                }
+            "
+        );
+    }
+}